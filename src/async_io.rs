@@ -0,0 +1,197 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Notify;
+use tracing::Instrument;
+
+use crate::encoding::decode_text;
+use crate::save::{temp_path, to_json};
+use crate::{json, telemetry, validate_dogs, Dog, GetDogsError, MyResult};
+
+/// The async version of [`get_dogs3`](crate::get_dogs3).
+///
+/// The file is read with tokio's file I/O so the runtime's
+/// worker threads aren't blocked, and it fails the same ways.
+/// Combine it with [`timeout`] and [`cancellable`]
+/// to limit how long it may take.
+pub async fn get_dogs_async(file_path: &str) -> MyResult<Vec<Dog>> {
+    let operation = telemetry::load(file_path, "json");
+    let start = Instant::now();
+    let result = load(file_path).instrument(operation.span.clone()).await;
+    telemetry::finish(&operation, start, &result);
+    result
+}
+
+async fn load(file_path: &str) -> MyResult<Vec<Dog>> {
+    let bytes = tokio::fs::read(file_path).await?;
+    telemetry::record_bytes(bytes.len());
+    let json = decode_text(&bytes)?;
+    let dogs = json::dogs_from_str(&json)?;
+    telemetry::record_dogs(dogs.len());
+    validate_dogs(&dogs)?;
+    Ok(dogs)
+}
+
+/// The async version of [`save_dogs`](crate::save_dogs).
+///
+/// If the returned future is dropped before it completes,
+/// for example because it was cancelled or timed out,
+/// the temporary file is removed and the target holds
+/// either the old dogs or the new ones.
+/// It holds the new ones if the temporary file had already been renamed over it,
+/// in which case only flushing the directory, which makes the rename
+/// survive a crash, was skipped.
+/// Like save_dogs, concurrent saves of one file each use their own temporary file.
+pub async fn save_dogs_async(file_path: &str, dogs: &[Dog]) -> MyResult<()> {
+    let operation = telemetry::save(file_path, "json", dogs.len());
+    let start = Instant::now();
+    let result = save(file_path, dogs)
+        .instrument(operation.span.clone())
+        .await;
+    telemetry::finish(&operation, start, &result);
+    result
+}
+
+async fn save(file_path: &str, dogs: &[Dog]) -> MyResult<()> {
+    let json = to_json(dogs).map_err(GetDogsError::CannotSerialize)?;
+    telemetry::record_bytes(json.len());
+
+    let target = Path::new(file_path);
+    let (file, temp) = create_temp(target).map_err(GetDogsError::CannotWrite)?;
+    write_synced(file, &json)
+        .await
+        .map_err(GetDogsError::CannotWrite)?;
+    // tokio would rename on a blocking thread that keeps going
+    // when the future is dropped, racing with the removal of the temporary file.
+    // Renaming here blocks briefly, but the rename has either
+    // happened or not at every point where the future can be dropped.
+    std::fs::rename(temp.path(), target).map_err(GetDogsError::CannotRename)?;
+    temp.keep();
+    sync_parent(target)
+        .await
+        .map_err(GetDogsError::CannotRename)
+}
+
+// This removes the temporary file when it goes out of scope,
+// which covers both errors and the future being dropped.
+// It uses blocking I/O because Drop can't be async,
+// but removing a file is quick.
+struct TempFile(Option<PathBuf>);
+
+impl TempFile {
+    fn path(&self) -> &Path {
+        self.0.as_deref().expect("temporary file is kept")
+    }
+
+    // Called once the file has been renamed, so there's nothing to remove.
+    fn keep(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Some(path) = &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// The file is created with blocking I/O for the same reason as the rename,
+// so that it is removed by TempFile however the future is dropped.
+// TempFile is only made once the file exists, so that a save whose
+// temporary file already existed doesn't remove someone else's.
+// See save::temp_path for why the file must be new.
+fn create_temp(target: &Path) -> std::io::Result<(tokio::fs::File, TempFile)> {
+    let path = temp_path(target);
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    Ok((tokio::fs::File::from_std(file), TempFile(Some(path))))
+}
+
+async fn write_synced(mut file: tokio::fs::File, contents: &[u8]) -> std::io::Result<()> {
+    file.write_all(contents).await?;
+    file.sync_all().await
+}
+
+#[cfg(unix)]
+async fn sync_parent(target: &Path) -> std::io::Result<()> {
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    tokio::fs::File::open(dir).await?.sync_all().await
+}
+
+#[cfg(not(unix))]
+async fn sync_parent(_target: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Fails with [`GetDogsError::TimedOut`] if a future
+/// doesn't complete within the limit.
+/// The future is dropped when the time is up.
+pub async fn timeout<T>(limit: Duration, future: impl Future<Output = MyResult<T>>) -> MyResult<T> {
+    match tokio::time::timeout(limit, future).await {
+        Ok(result) => result,
+        Err(_) => Err(GetDogsError::TimedOut(limit)),
+    }
+}
+
+/// Lets one task cancel operations running in others.
+/// Clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelState>,
+}
+
+#[derive(Debug, Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels every operation using this token, now and in the future.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    async fn cancelled(&self) {
+        // Registering before checking the flag means a cancel
+        // that happens in between still wakes this task.
+        let notified = self.inner.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if !self.is_cancelled() {
+            notified.await;
+        }
+    }
+}
+
+/// Fails with [`GetDogsError::Cancelled`] if the token is cancelled
+/// before a future completes. The future is dropped when that happens.
+pub async fn cancellable<T>(
+    token: &CancelToken,
+    future: impl Future<Output = MyResult<T>>,
+) -> MyResult<T> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(GetDogsError::Cancelled),
+        result = future => result,
+    }
+}
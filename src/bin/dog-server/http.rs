@@ -0,0 +1,157 @@
+use rust_error_handling::ProblemDetails;
+use serde::Serialize;
+use std::io::{self, BufRead, Write};
+
+// This is just enough HTTP/1.1 for the dogs API.
+// Each connection carries one request and is then closed,
+// so there is no need to support keep-alive or chunked bodies.
+
+// Larger bodies are rejected rather than read into memory.
+const MAX_BODY: usize = 1024 * 1024;
+
+pub struct Request {
+    pub method: String,
+    // The path without any query string.
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    // A value that can't be serialized is the server's fault,
+    // so it is answered with a 500 problem rather than a panic.
+    pub fn json(status: u16, value: &impl Serialize) -> Self {
+        match serde_json::to_string(value) {
+            Ok(body) => Response {
+                status,
+                content_type: "application/json",
+                body,
+            },
+            Err(e) => Response::problem(&ProblemDetails::other(
+                500,
+                "Response cannot be serialized",
+                e.to_string(),
+            )),
+        }
+    }
+
+    pub fn problem(problem: &ProblemDetails) -> Self {
+        Response {
+            status: problem.status,
+            content_type: "application/problem+json",
+            body: problem.to_json(),
+        }
+    }
+
+    pub fn empty(status: u16) -> Self {
+        Response {
+            status,
+            content_type: "text/plain",
+            body: String::new(),
+        }
+    }
+}
+
+// Requests that can't be understood are answered
+// with a problem response rather than an io::Error.
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let bad = |detail: &str| {
+        let problem = ProblemDetails::other(400, "Bad request", detail);
+        Ok(Err(Response::problem(&problem)))
+    };
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/1.") => {
+            (method.to_string(), target)
+        }
+        _ => return bad("the request line is malformed"),
+    };
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return bad("the headers end unexpectedly");
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = match value.trim().parse() {
+                    Ok(length) => length,
+                    Err(_) => return bad("the Content-Length header is not a number"),
+                };
+            }
+        }
+    }
+    if length > MAX_BODY {
+        let detail = format!("the body is larger than {} bytes", MAX_BODY);
+        let problem = ProblemDetails::other(413, "Request body too large", detail);
+        return Ok(Err(Response::problem(&problem)));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Ok(Request { method, path, body }))
+}
+
+pub fn write_response(writer: &mut impl Write, response: &Response) -> io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    writer.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+// Names in paths are percent-encoded, such as "Sir%20Woofs".
+// This returns None if the result isn't valid UTF-8.
+pub fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        match (b, tail) {
+            (b'%', [hi, lo, tail @ ..]) => {
+                let hex = [*hi, *lo];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+                rest = tail;
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
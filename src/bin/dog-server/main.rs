@@ -0,0 +1,107 @@
+use rust_error_handling::JsonFileStore;
+use std::io::{BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::time::Duration;
+
+mod http;
+mod routes;
+
+const USAGE: &str = "\
+usage: dog-server [options]
+
+Serves the dogs in a JSON file over HTTP:
+  GET    /dogs         list the dogs
+  POST   /dogs         add the dog in the body
+  GET    /dogs/{name}  get one dog
+  PUT    /dogs/{name}  replace a dog with the one in the body
+  DELETE /dogs/{name}  remove a dog
+  GET    /metrics      count the loads, saves and failures for Prometheus
+
+Errors are answered with application/problem+json bodies.
+
+options:
+  -f, --file PATH   the JSON file to serve (default ./dogs.json)
+      --addr ADDR   the address to listen on (default 127.0.0.1:8080)
+  -h, --help        print this message";
+
+struct Args {
+    file: String,
+    addr: String,
+}
+
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        file: "./dogs.json".to_string(),
+        addr: "127.0.0.1:8080".to_string(),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match arg.as_str() {
+            "-f" | "--file" => parsed.file = value("--file")?,
+            "--addr" => parsed.addr = value("--addr")?,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+// Requests are handled one at a time, which keeps
+// concurrent requests from overwriting each other's changes to the file.
+fn main() {
+    let args = match parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let listener = match TcpListener::bind(&args.addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: cannot listen on {}: {}", args.addr, e);
+            process::exit(1);
+        }
+    };
+    // Port 0 picks a free port, so print the one that was chosen.
+    if let Ok(addr) = listener.local_addr() {
+        println!("listening on http://{}", addr);
+        let _ = std::io::stdout().flush();
+    }
+
+    // A file that disappears while the server runs is reported
+    // as unavailable instead of silently starting an empty roster.
+    let mut store = JsonFileStore::new(args.file).must_exist();
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = serve(&mut store, stream) {
+                    eprintln!("error: connection failed: {}", e);
+                }
+            }
+            Err(e) => eprintln!("error: cannot accept connection: {}", e),
+        }
+    }
+}
+
+fn serve(store: &mut JsonFileStore, stream: TcpStream) -> std::io::Result<()> {
+    // A client that stops sending mustn't hold up everyone else.
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match http::read_request(&mut reader)? {
+        Ok(request) => routes::handle(store, &request),
+        Err(response) => response,
+    };
+    let mut stream = stream;
+    http::write_response(&mut stream, &response)
+}
@@ -0,0 +1,94 @@
+use rust_error_handling::{Dog, DogStore, MyResult, ProblemDetails};
+
+use crate::http::{percent_decode, Request, Response};
+
+// The API has two resources:
+//   /dogs         GET lists the dogs and POST adds one
+//   /dogs/{name}  GET, PUT and DELETE act on one dog
+// and GET /metrics reports the counts of loads, saves and failures.
+// Store failures become problem responses
+// with the status chosen by GetDogsError::http_status.
+pub fn handle(store: &mut dyn DogStore, request: &Request) -> Response {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let name = match segments.as_slice() {
+        #[cfg(feature = "metrics")]
+        ["metrics"] if request.method == "GET" => return metrics(),
+        ["dogs"] => None,
+        ["dogs", name] => match percent_decode(name) {
+            Some(name) => Some(name),
+            None => return other(400, "Bad request", "the dog name is not valid UTF-8"),
+        },
+        _ => {
+            let detail = format!("there is no resource at {}", request.path);
+            return other(404, "Not found", detail);
+        }
+    };
+
+    let result = match (request.method.as_str(), name) {
+        ("GET", None) => store.list().map(|dogs| Response::json(200, &dogs)),
+        ("POST", None) => match parse_dog(&request.body) {
+            Ok(dog) => store.insert(dog.clone()).map(|_| Response::json(201, &dog)),
+            Err(response) => return response,
+        },
+        ("GET", Some(name)) => match store.get(&name) {
+            Ok(Some(dog)) => Ok(Response::json(200, &dog)),
+            Ok(None) => {
+                let detail = format!("no dog is named {:?}", name);
+                return other(404, "Dog not found", detail);
+            }
+            Err(e) => Err(e),
+        },
+        ("PUT", Some(name)) => match parse_dog(&request.body) {
+            Ok(dog) if dog.name() != name => {
+                let detail = format!(
+                    "the body names {:?} but the path names {:?}",
+                    dog.name(),
+                    name
+                );
+                return other(400, "Bad request", detail);
+            }
+            Ok(dog) => store.update(dog.clone()).map(|_| Response::json(200, &dog)),
+            Err(response) => return response,
+        },
+        ("DELETE", Some(name)) => store.delete(&name).map(|_| Response::empty(204)),
+        (method, _) => {
+            let detail = format!("{} is not supported for {}", method, request.path);
+            return other(405, "Method not allowed", detail);
+        }
+    };
+    respond(result)
+}
+
+fn respond(result: MyResult<Response>) -> Response {
+    match result {
+        Ok(response) => response,
+        Err(e) => {
+            // Requests the client got wrong are routine,
+            // but the administrator needs to see failures of the server.
+            if e.http_status() >= 500 {
+                eprintln!("error: {:#}", e);
+            }
+            Response::problem(&ProblemDetails::new(&e))
+        }
+    }
+}
+
+// A body that isn't a dog is the client's fault, so it is 400
+// even though a bad dogs file is 500.
+fn parse_dog(body: &[u8]) -> Result<Dog, Response> {
+    serde_json::from_slice(body).map_err(|e| other(400, "Request body is not a dog", e.to_string()))
+}
+
+// This is the content type Prometheus expects for its text format.
+#[cfg(feature = "metrics")]
+fn metrics() -> Response {
+    Response {
+        status: 200,
+        content_type: "text/plain; version=0.0.4",
+        body: rust_error_handling::Metrics::global().to_prometheus(),
+    }
+}
+
+fn other(status: u16, title: &str, detail: impl Into<String>) -> Response {
+    Response::problem(&ProblemDetails::other(status, title, detail))
+}
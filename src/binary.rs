@@ -0,0 +1,121 @@
+// MessagePack and CBOR are binary formats for the same data as JSON.
+// Files in these formats hold a bare array of dogs
+// rather than the versioned object written by save_dogs.
+// Each format is behind a cargo feature of the same name.
+
+use crate::save::write_atomically;
+use crate::validate::validate_dogs;
+use crate::{telemetry, Dog, FileFormat, FileSystem, GetDogsError, MyResult, RealFileSystem};
+
+/// Reads dogs from a MessagePack file and validates them,
+/// like [`get_dogs3`](crate::get_dogs3) does for JSON.
+///
+/// Data that isn't MessagePack or doesn't describe dogs
+/// is reported as [`GetDogsError::BadMessagePack`]
+/// so it can be told apart from failures to read the file.
+#[cfg(feature = "msgpack")]
+pub fn get_dogs_msgpack(file_path: &str) -> MyResult<Vec<Dog>> {
+    get_dogs_msgpack_from(&RealFileSystem, file_path)
+}
+
+/// Like [`get_dogs_msgpack`], but reads the file from a [`FileSystem`].
+#[cfg(feature = "msgpack")]
+pub fn get_dogs_msgpack_from(fs: &dyn FileSystem, file_path: &str) -> MyResult<Vec<Dog>> {
+    load(fs, file_path, FileFormat::MessagePack, msgpack_from_slice)
+}
+
+/// Writes dogs to a MessagePack file, replacing it atomically
+/// like [`save_dogs`](crate::save_dogs).
+#[cfg(feature = "msgpack")]
+pub fn save_dogs_msgpack(file_path: &str, dogs: &[Dog]) -> MyResult<()> {
+    save(file_path, dogs, FileFormat::MessagePack, msgpack_to_vec)
+}
+
+/// Reads dogs from a CBOR file and validates them,
+/// like [`get_dogs3`](crate::get_dogs3) does for JSON.
+///
+/// Data that isn't CBOR or doesn't describe dogs
+/// is reported as [`GetDogsError::BadCbor`]
+/// so it can be told apart from failures to read the file.
+#[cfg(feature = "cbor")]
+pub fn get_dogs_cbor(file_path: &str) -> MyResult<Vec<Dog>> {
+    get_dogs_cbor_from(&RealFileSystem, file_path)
+}
+
+/// Like [`get_dogs_cbor`], but reads the file from a [`FileSystem`].
+#[cfg(feature = "cbor")]
+pub fn get_dogs_cbor_from(fs: &dyn FileSystem, file_path: &str) -> MyResult<Vec<Dog>> {
+    load(fs, file_path, FileFormat::Cbor, cbor_from_slice)
+}
+
+/// Writes dogs to a CBOR file, replacing it atomically
+/// like [`save_dogs`](crate::save_dogs).
+#[cfg(feature = "cbor")]
+pub fn save_dogs_cbor(file_path: &str, dogs: &[Dog]) -> MyResult<()> {
+    save(file_path, dogs, FileFormat::Cbor, cbor_to_vec)
+}
+
+fn load(
+    fs: &dyn FileSystem,
+    file_path: &str,
+    format: FileFormat,
+    decode: fn(&[u8]) -> MyResult<Vec<Dog>>,
+) -> MyResult<Vec<Dog>> {
+    telemetry::traced(telemetry::load(file_path, format), || {
+        let bytes = fs.read(file_path)?;
+        telemetry::record_bytes(bytes.len());
+        let dogs = decode(&bytes)?;
+        telemetry::record_dogs(dogs.len());
+        validate_dogs(&dogs)?;
+        Ok(dogs)
+    })
+}
+
+fn save(
+    file_path: &str,
+    dogs: &[Dog],
+    format: FileFormat,
+    encode: fn(&[Dog]) -> MyResult<Vec<u8>>,
+) -> MyResult<()> {
+    telemetry::traced(telemetry::save(file_path, format, dogs.len()), || {
+        write_atomically(file_path, &encode(dogs)?)
+    })
+}
+
+// A zero-length file is reported like an empty text file
+// rather than as truncated data.
+#[cfg(feature = "msgpack")]
+pub(crate) fn msgpack_from_slice(bytes: &[u8]) -> MyResult<Vec<Dog>> {
+    if bytes.is_empty() {
+        return Err(GetDogsError::Empty);
+    }
+    rmp_serde::from_slice(bytes).map_err(GetDogsError::BadMessagePack)
+}
+
+// Fields are written with their names, like in the text formats,
+// because positions would shift when optional fields are missing.
+#[cfg(feature = "msgpack")]
+pub(crate) fn msgpack_to_vec(dogs: &[Dog]) -> MyResult<Vec<u8>> {
+    rmp_serde::to_vec_named(dogs).map_err(|e| GetDogsError::CannotEncode {
+        format: FileFormat::MessagePack,
+        source: e.into(),
+    })
+}
+
+#[cfg(feature = "cbor")]
+pub(crate) fn cbor_from_slice(bytes: &[u8]) -> MyResult<Vec<Dog>> {
+    if bytes.is_empty() {
+        return Err(GetDogsError::Empty);
+    }
+    ciborium::from_reader(bytes).map_err(GetDogsError::BadCbor)
+}
+
+#[cfg(feature = "cbor")]
+pub(crate) fn cbor_to_vec(dogs: &[Dog]) -> MyResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(dogs, &mut bytes).map_err(|e| GetDogsError::CannotEncode {
+        format: FileFormat::Cbor,
+        source: e.into(),
+    })?;
+    Ok(bytes)
+}
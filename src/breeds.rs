@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{Dog, FileSystem, GetDogsError, MyResult, RealFileSystem};
+
+// The registry that is built into the library.
+// See the comments at the top of the file for its format.
+const BUNDLED: &str = include_str!("../data/breeds.txt");
+
+/// The known breeds and the aliases people use for them,
+/// such as "GSP" for "German Shorthaired Pointer".
+///
+/// Names are matched ignoring case, hyphens and extra spaces.
+#[derive(Clone, Debug, Default)]
+pub struct BreedRegistry {
+    // Maps normalized names and aliases to canonical names.
+    names: HashMap<String, String>,
+}
+
+/// What [`canonicalize_breeds`] does with breeds that aren't in the registry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BreedMode {
+    /// Unknown breeds are left as they are.
+    Lenient,
+    /// Unknown breeds fail with [`GetDogsError::UnknownBreeds`].
+    Strict,
+}
+
+/// A dog whose breed isn't in the registry.
+/// The suggestion is the closest known breed, if any is close.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownBreed {
+    pub index: usize,
+    pub breed: String,
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownBreed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dog {} has unknown breed {:?}", self.index, self.breed)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean {:?}?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl BreedRegistry {
+    /// The registry bundled with the library.
+    pub fn bundled() -> Self {
+        let mut registry = BreedRegistry::default();
+        // The bundled file is checked by the tests, so this cannot fail.
+        registry
+            .add_text(BUNDLED)
+            .expect("bundled breeds are valid");
+        registry
+    }
+
+    /// Adds the breeds in a file with the same format as the bundled one.
+    ///
+    /// A name in the file replaces any existing meaning of that name,
+    /// so users can add breeds, add aliases to known breeds,
+    /// or point an alias at a different breed.
+    pub fn with_overrides(mut self, file_path: &str) -> MyResult<Self> {
+        let text = crate::read_text(&RealFileSystem, file_path)?;
+        self.add_text(&text)?;
+        Ok(self)
+    }
+
+    /// Like [`with_overrides`](BreedRegistry::with_overrides),
+    /// but reads the file from a [`FileSystem`].
+    pub fn with_overrides_from(mut self, fs: &dyn FileSystem, file_path: &str) -> MyResult<Self> {
+        let text = crate::read_text(fs, file_path)?;
+        self.add_text(&text)?;
+        Ok(self)
+    }
+
+    /// Adds a breed and its aliases.
+    pub fn add(&mut self, canonical: &str, aliases: &[&str]) {
+        let canonical = canonical.trim();
+        for name in std::iter::once(canonical).chain(aliases.iter().copied()) {
+            self.names.insert(normalize(name), canonical.to_string());
+        }
+    }
+
+    fn add_text(&mut self, text: &str) -> MyResult<()> {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (canonical, aliases) = line.split_once(':').unwrap_or((line, ""));
+            let aliases: Vec<&str> = aliases
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .collect();
+            if canonical.trim().is_empty() {
+                return Err(GetDogsError::BadRegistry {
+                    line: index + 1,
+                    reason: "the breed name is missing".to_string(),
+                });
+            }
+            self.add(canonical, &aliases);
+        }
+        Ok(())
+    }
+
+    /// Returns the canonical name for a breed or one of its aliases.
+    pub fn canonicalize(&self, breed: &str) -> Option<&str> {
+        self.names.get(&normalize(breed)).map(String::as_str)
+    }
+
+    /// Returns the canonical name of the known breed or alias
+    /// that is closest to an unknown breed by edit distance.
+    /// Nothing is suggested when every breed is too different
+    /// for the suggestion to be useful.
+    pub fn suggest(&self, breed: &str) -> Option<&str> {
+        let breed = normalize(breed);
+        // This allows about one typo for every three characters.
+        let limit = (breed.chars().count() / 3).max(1);
+        self.names
+            .iter()
+            .map(|(name, canonical)| (edit_distance(&breed, name), canonical))
+            .filter(|&(distance, _)| distance <= limit)
+            // Ties go to the alphabetically first name so results are stable.
+            .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(b.1)))
+            .map(|(_, canonical)| canonical.as_str())
+    }
+}
+
+/// Replaces each dog's breed with its canonical name.
+///
+/// In strict mode every dog with an unknown breed is reported
+/// and no breeds are changed.
+pub fn canonicalize_breeds(
+    dogs: &mut [Dog],
+    registry: &BreedRegistry,
+    mode: BreedMode,
+) -> MyResult<()> {
+    let mut unknown = Vec::new();
+    for (index, dog) in dogs.iter().enumerate() {
+        if registry.canonicalize(&dog.breed).is_none() {
+            unknown.push(UnknownBreed {
+                index,
+                breed: dog.breed.clone(),
+                suggestion: registry.suggest(&dog.breed).map(String::from),
+            });
+        }
+    }
+    if mode == BreedMode::Strict && !unknown.is_empty() {
+        return Err(GetDogsError::UnknownBreeds(unknown));
+    }
+    for dog in dogs.iter_mut() {
+        if let Some(canonical) = registry.canonicalize(&dog.breed) {
+            dog.breed = canonical.to_string();
+        }
+    }
+    Ok(())
+}
+
+fn normalize(name: &str) -> String {
+    name.replace('-', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// This is the Levenshtein distance: the number of characters
+// that must be inserted, deleted or replaced to turn one string into the other.
+// It keeps only one row of the usual table.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let replace = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = replace.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,285 @@
+// This parses the command line by hand.
+// The grammar is small enough that a parsing library isn't needed.
+
+use rust_error_handling::{DedupeStrategy, FileFormat, UniqueKey};
+use tracing::level_filters::LevelFilter;
+
+pub const USAGE: &str = "\
+usage: rust-error-handling [--file PATH] [--format FORMAT] COMMAND [ARGS]
+
+commands:
+  list                  print every dog
+  query QUERY           print the dogs matching a query, such as
+                        'breed = Whippet and name ~ \"^C\" sort by name limit 5'
+  add NAME BREED        add a dog with a new name and save the file
+  remove NAME           remove every dog with the given name and save the file
+  validate              report every bad dog instead of stopping at the first
+  convert OUTPUT        write the dogs to a file in the format of its extension,
+                        warning about fields that dogs don't have
+  stats                 count the dogs and breeds
+  dedupe KEY KEEP       combine dogs with the same KEY (name, name+breed or microchip)
+                        keeping the first, the last, or merging their fields
+                        (KEEP is first, last or merge) and save the file
+
+options:
+  -f, --file PATH       the dogs file to use (default ./dogs.json)
+      --format FORMAT   text, json or table (default text)
+      --input-format F  json, ndjson, yaml, toml, csv, msgpack or cbor
+                        (default from the file extension)
+      --output-format F the format written by convert (default from the extension)
+      --json-errors     write errors to stderr as JSON reports
+      --breeds PATH     a file of extra breeds and aliases, one breed per line
+                        written as BREED: ALIAS, ALIAS
+      --strict-breeds   fail on breeds that aren't known instead of keeping them
+      --unique KEY      fail when dogs share a KEY (name, name+breed or microchip);
+                        add and validate check names unless this gives another key
+      --log-level LEVEL off, error, warn, info, debug or trace
+                        (default from the RUST_LOG environment variable, or off)
+      --log-format F    text or json logs on stderr (default text)
+      --metrics PATH    write counts of loads, saves and failures to a file
+                        in the Prometheus text format when the tool exits
+  -h, --help            print this message
+
+exit codes:
+  0  success
+  1  other failure, such as removing a dog that doesn't exist
+  2  usage error
+  3  the file could not be read for another reason
+  4  the file is not valid JSON or doesn't describe dogs
+  5  some dogs break the validation rules
+  6  the dogs could not be serialized
+  7  the temporary file could not be written
+  8  the temporary file could not be renamed over the target
+  9  the file is not valid YAML or doesn't describe dogs
+  10 the file is not valid TOML or doesn't describe dogs
+  11 the file is not valid CSV or doesn't describe dogs
+  12 a field of a dog has a bad value, such as a date that doesn't exist
+  13 the file has an unsupported format version
+  14 an older file could not be upgraded to the current version
+  15 a dog store operation failed (not used by this tool)
+  16 the file does not exist
+  17 permission to read the file was denied
+  18 the file is not valid UTF-8 or UTF-16
+  19 the file is empty
+  20 an async operation timed out (not used by this tool)
+  21 an async operation was cancelled (not used by this tool)
+  22 some dogs have the same name, or the same --unique key
+  23 some dogs have unknown breeds (only with --strict-breeds)
+  24 the --breeds file has a line without a breed name
+  25 the query is malformed
+  26 the file is not valid MessagePack or doesn't describe dogs
+  27 the dogs could not be written in the output format
+  28 the file is not valid CBOR or doesn't describe dogs
+  29 the JSON around the dogs is broken, such as a trailing comma";
+
+const DEFAULT_FILE: &str = "./dogs.json";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Table,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    List,
+    Query {
+        query: String,
+    },
+    Add {
+        name: String,
+        breed: String,
+    },
+    Remove {
+        name: String,
+    },
+    Validate,
+    Convert {
+        output: String,
+    },
+    Stats,
+    Dedupe {
+        key: UniqueKey,
+        strategy: DedupeStrategy,
+    },
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub file: String,
+    pub format: Format,
+    pub input_format: Option<FileFormat>,
+    pub output_format: Option<FileFormat>,
+    pub json_errors: bool,
+    pub breeds: Option<String>,
+    pub strict_breeds: bool,
+    pub unique: Option<UniqueKey>,
+    pub log_level: Option<LevelFilter>,
+    pub log_format: LogFormat,
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<String>,
+    pub command: Command,
+}
+
+// Options can appear before or after the command.
+// The error is a message describing the usage mistake.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut file = DEFAULT_FILE.to_string();
+    let mut format = Format::Text;
+    let mut input_format = None;
+    let mut output_format = None;
+    let mut json_errors = false;
+    let mut breeds = None;
+    let mut strict_breeds = false;
+    let mut unique = None;
+    let mut log_level = None;
+    let mut log_format = LogFormat::Text;
+    #[cfg(feature = "metrics")]
+    let mut metrics = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Support both "--file PATH" and "--file=PATH".
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| match inline {
+            Some(v) => Ok(v.to_string()),
+            None => args
+                .next()
+                .ok_or_else(|| format!("{} requires a value", name)),
+        };
+        match flag.as_str() {
+            "-f" | "--file" => file = value("--file")?,
+            "--format" => format = parse_format(&value("--format")?)?,
+            "--input-format" => input_format = Some(value("--input-format")?.parse()?),
+            "--output-format" => output_format = Some(value("--output-format")?.parse()?),
+            "--json-errors" => json_errors = true,
+            "--breeds" => breeds = Some(value("--breeds")?),
+            "--strict-breeds" => strict_breeds = true,
+            "--unique" => unique = Some(value("--unique")?.parse()?),
+            "--log-level" => log_level = Some(parse_log_level(&value("--log-level")?)?),
+            "--log-format" => log_format = parse_log_format(&value("--log-format")?)?,
+            #[cfg(feature = "metrics")]
+            "--metrics" => metrics = Some(value("--metrics")?),
+            "-h" | "--help" => {
+                return Ok(Args {
+                    file,
+                    format,
+                    input_format,
+                    output_format,
+                    json_errors,
+                    breeds,
+                    strict_breeds,
+                    unique,
+                    log_level,
+                    log_format,
+                    #[cfg(feature = "metrics")]
+                    metrics,
+                    command: Command::Help,
+                })
+            }
+            _ if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option {}", flag))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let command = parse_command(positional)?;
+    Ok(Args {
+        file,
+        format,
+        input_format,
+        output_format,
+        json_errors,
+        breeds,
+        strict_breeds,
+        unique,
+        log_level,
+        log_format,
+        #[cfg(feature = "metrics")]
+        metrics,
+        command,
+    })
+}
+
+fn parse_format(s: &str) -> Result<Format, String> {
+    match s {
+        "text" => Ok(Format::Text),
+        "json" => Ok(Format::Json),
+        "table" => Ok(Format::Table),
+        _ => Err(format!("unknown format {:?}", s)),
+    }
+}
+
+fn parse_log_level(s: &str) -> Result<LevelFilter, String> {
+    s.parse().map_err(|_| format!("unknown log level {:?}", s))
+}
+
+fn parse_log_format(s: &str) -> Result<LogFormat, String> {
+    match s {
+        "text" => Ok(LogFormat::Text),
+        "json" => Ok(LogFormat::Json),
+        _ => Err(format!("unknown log format {:?}", s)),
+    }
+}
+
+fn parse_command(positional: Vec<String>) -> Result<Command, String> {
+    let mut positional = positional.into_iter();
+    let name = match positional.next() {
+        Some(name) => name,
+        None => return Err("missing command".to_string()),
+    };
+    let rest: Vec<String> = positional.collect();
+    let expect = |count: usize| {
+        if rest.len() == count {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} expects {} argument(s), but got {}",
+                name,
+                count,
+                rest.len()
+            ))
+        }
+    };
+
+    match name.as_str() {
+        "list" => expect(0).map(|_| Command::List),
+        "query" => expect(1).map(|_| Command::Query {
+            query: rest[0].clone(),
+        }),
+        "add" => expect(2).map(|_| Command::Add {
+            name: rest[0].clone(),
+            breed: rest[1].clone(),
+        }),
+        "remove" => expect(1).map(|_| Command::Remove {
+            name: rest[0].clone(),
+        }),
+        "validate" => expect(0).map(|_| Command::Validate),
+        "convert" => expect(1).map(|_| Command::Convert {
+            output: rest[0].clone(),
+        }),
+        "stats" => expect(0).map(|_| Command::Stats),
+        "dedupe" => {
+            expect(2)?;
+            Ok(Command::Dedupe {
+                key: rest[0].parse()?,
+                strategy: rest[1].parse()?,
+            })
+        }
+        "help" => Ok(Command::Help),
+        _ => Err(format!("unknown command {:?}", name)),
+    }
+}
@@ -0,0 +1,439 @@
+use rust_error_handling::{
+    canonicalize_breeds, check_unique, dedupe, find_duplicates, get_dogs, load_dogs,
+    read_for_conversion, save_dogs_as, validate_dog, BreedMode, BreedRegistry, Context,
+    DedupeStrategy, Dog, ElementError, FileFormat, GetDogsError, Loaded, Mode, MyResult, Query,
+    UniqueKey,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::cli::{Args, Command, Format};
+
+// Commands fail either because loading or saving dogs failed,
+// or for a reason that is specific to the command.
+// Dogs failures hold the path of the file involved
+// when it isn't the file given by --file.
+// Reported is for failures the command has already printed,
+// which only need to set the exit code.
+#[derive(Debug)]
+pub enum Failure {
+    Dogs(GetDogsError, Option<String>),
+    Other(String),
+    Reported(i32),
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Dogs(e, _) => e.exit_code(),
+            Failure::Other(_) => 1,
+            Failure::Reported(code) => *code,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Dogs(e, _) => write!(f, "{}", e),
+            Failure::Other(message) => write!(f, "{}", message),
+            Failure::Reported(code) => write!(f, "failed with exit code {}", code),
+        }
+    }
+}
+
+// This enables using the ? operator on MyResult values in the commands.
+impl From<GetDogsError> for Failure {
+    fn from(other: GetDogsError) -> Self {
+        Failure::Dogs(other, None)
+    }
+}
+
+pub fn run(args: &Args) -> Result<(), Failure> {
+    if args.command == Command::Help {
+        println!("{}", crate::cli::USAGE);
+        return Ok(());
+    }
+    let file = args.file.as_str();
+    let input = args.input_format;
+    let checks = Checks::new(args)?;
+    match args.command {
+        Command::List => list(file, input, &checks, args.format),
+        Command::Query { ref query } => query_dogs(file, input, &checks, query, args.format),
+        Command::Add {
+            ref name,
+            ref breed,
+        } => add(file, input, name, breed, &checks),
+        Command::Remove { ref name } => remove(file, input, name),
+        Command::Validate => validate(file, input, &checks, args.format),
+        Command::Convert { ref output } => {
+            convert(file, input, &checks, output, args.output_format)
+        }
+        Command::Stats => stats(file, input, &checks, args.format),
+        Command::Dedupe { key, strategy } => dedupe_dogs(file, input, key, strategy),
+        Command::Help => unreachable!("help is handled above"),
+    }
+}
+
+// These are the checks of the options that apply to every command that loads dogs.
+// Loaded breeds are replaced with their canonical names,
+// so "GSP" is listed and counted as "German Shorthaired Pointer".
+// Dogs are only checked for duplicates when --unique gives a key.
+struct Checks {
+    registry: BreedRegistry,
+    mode: BreedMode,
+    unique: Option<UniqueKey>,
+}
+
+impl Checks {
+    fn new(args: &Args) -> Result<Self, Failure> {
+        let mut registry = BreedRegistry::bundled();
+        if let Some(path) = &args.breeds {
+            registry = registry
+                .with_overrides(path)
+                .with_context(|| format!("loading breeds from {}", path))
+                .map_err(|e| Failure::Dogs(e, Some(path.clone())))?;
+        }
+        let mode = if args.strict_breeds {
+            BreedMode::Strict
+        } else {
+            BreedMode::Lenient
+        };
+        Ok(Checks {
+            registry,
+            mode,
+            unique: args.unique,
+        })
+    }
+
+    fn canonicalize(&self, dogs: &mut [Dog]) -> MyResult<()> {
+        canonicalize_breeds(dogs, &self.registry, self.mode)
+    }
+
+    // Without --unique, adding and validating dogs still reject duplicate names.
+    fn unique_key(&self) -> UniqueKey {
+        self.unique.unwrap_or(UniqueKey::Name)
+    }
+
+    fn check(&self, dogs: &mut [Dog]) -> MyResult<()> {
+        self.canonicalize(dogs)?;
+        match self.unique {
+            Some(key) => check_unique(dogs, key),
+            None => Ok(()),
+        }
+    }
+}
+
+fn list(
+    file: &str,
+    input: Option<FileFormat>,
+    checks: &Checks,
+    format: Format,
+) -> Result<(), Failure> {
+    let dogs = load(file, input, checks)?;
+    print_dogs(&dogs, format);
+    Ok(())
+}
+
+// The query is parsed before the file is loaded
+// so a mistake in it is reported even if the file is bad too.
+fn query_dogs(
+    file: &str,
+    input: Option<FileFormat>,
+    checks: &Checks,
+    query: &str,
+    format: Format,
+) -> Result<(), Failure> {
+    let query = Query::parse(query).map_err(GetDogsError::from)?;
+    let dogs = query.run(load(file, input, checks)?);
+    print_dogs(&dogs, format);
+    Ok(())
+}
+
+fn print_dogs(dogs: &[Dog], format: Format) {
+    match format {
+        Format::Text => {
+            for dog in dogs {
+                println!("{} ({})", dog.name(), dog.breed());
+            }
+        }
+        Format::Json => print_json(&dogs),
+        Format::Table => {
+            let rows: Vec<[&str; 2]> = dogs.iter().map(|d| [d.name(), d.breed()]).collect();
+            print_table(["NAME", "BREED"], &rows);
+        }
+    }
+}
+
+// Adding, removing and deduping write the dogs back in the format they were read in.
+// Adding to a file that doesn't exist yet creates it.
+// Only the new dog's breed is canonicalized and only its key is checked,
+// so adding a dog doesn't rewrite the others
+// or fail because of duplicates already in the file.
+fn add(
+    file: &str,
+    input: Option<FileFormat>,
+    name: &str,
+    breed: &str,
+    checks: &Checks,
+) -> Result<(), Failure> {
+    let mut dogs = match get_dogs(file, input) {
+        Err(GetDogsError::NotFound(_)) => Vec::new(),
+        result => result.with_context(|| format!("loading dogs from {}", file))?,
+    };
+    let mut dog = [Dog::new(name, breed)];
+    checks.canonicalize(&mut dog)?;
+    let [dog] = dog;
+    let violations = validate_dog(dogs.len(), &dog);
+    if !violations.is_empty() {
+        return Err(GetDogsError::Invalid(violations).into());
+    }
+    dogs.push(dog);
+    let added = dogs.len() - 1;
+    let duplicates: Vec<_> = find_duplicates(&dogs, checks.unique_key())
+        .into_iter()
+        .filter(|duplicate| duplicate.second == added)
+        .collect();
+    if !duplicates.is_empty() {
+        return Err(GetDogsError::Duplicates {
+            key: checks.unique_key(),
+            duplicates,
+        }
+        .into());
+    }
+    save(file, input, &dogs)?;
+    Ok(())
+}
+
+fn remove(file: &str, input: Option<FileFormat>, name: &str) -> Result<(), Failure> {
+    let mut dogs = get_dogs(file, input).with_context(|| format!("loading dogs from {}", file))?;
+    let before = dogs.len();
+    dogs.retain(|dog| dog.name() != name);
+    if dogs.len() == before {
+        return Err(Failure::Other(format!("no dog is named {:?}", name)));
+    }
+    save(file, input, &dogs)?;
+    Ok(())
+}
+
+// This loads JSON files in lenient mode so every bad dog is reported.
+// Other formats are loaded strictly.
+// Then the good dogs are checked against the breed registry
+// and for duplicates, which can find more bad dogs.
+// The exit code is the one for the first bad dog.
+fn validate(
+    file: &str,
+    input: Option<FileFormat>,
+    checks: &Checks,
+    format: Format,
+) -> Result<(), Failure> {
+    let detected = input.or_else(|| FileFormat::from_path(file));
+    let Loaded { mut dogs, errors } = match detected {
+        None | Some(FileFormat::Json) => {
+            load_dogs(file, Mode::Lenient).with_context(|| format!("loading dogs from {}", file))?
+        }
+        Some(_) => Loaded {
+            dogs: get_dogs(file, input).with_context(|| format!("loading dogs from {}", file))?,
+            errors: Vec::new(),
+        },
+    };
+
+    let positions = good_positions(dogs.len(), &errors);
+    let mut problems = Vec::new();
+    if let Err(e) = checks.canonicalize(&mut dogs) {
+        problems.push(in_file(e, &positions));
+    }
+    if let Err(e) = check_unique(&dogs, checks.unique_key()) {
+        problems.push(in_file(e, &positions));
+    }
+    let bad_dogs: Vec<(usize, String)> = problems.iter().flat_map(problem_dogs).collect();
+
+    // A dog can be both a duplicate and have an unknown breed.
+    let mut bad_positions: Vec<usize> = bad_dogs.iter().map(|(index, _)| *index).collect();
+    bad_positions.sort_unstable();
+    bad_positions.dedup();
+    let valid = dogs.len() - bad_positions.len();
+    let invalid = errors.len() + bad_positions.len();
+    match format {
+        Format::Json => {
+            let mut messages: Vec<(usize, String)> = errors
+                .iter()
+                .map(|e| (e.index, e.error.to_string()))
+                .chain(bad_dogs)
+                .collect();
+            messages.sort_by_key(|(index, _)| *index);
+            let errors: Vec<_> = messages
+                .iter()
+                .map(|(index, message)| serde_json::json!({ "index": index, "message": message }))
+                .collect();
+            print_json(&serde_json::json!({ "valid": valid, "errors": errors }));
+        }
+        Format::Text | Format::Table => {
+            for e in &errors {
+                println!("{}", e);
+            }
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            println!("{} valid, {} invalid", valid, invalid);
+        }
+    }
+    match (errors.first(), problems.first()) {
+        (Some(first), _) => Err(Failure::Reported(first.error.exit_code())),
+        (None, Some(first)) => Err(Failure::Reported(first.exit_code())),
+        (None, None) => Ok(()),
+    }
+}
+
+// The positions in the file of the dogs that loaded,
+// which skip those of the bad dogs.
+fn good_positions(count: usize, errors: &[ElementError]) -> Vec<usize> {
+    (0..)
+        .filter(|i| !errors.iter().any(|e| e.index == *i))
+        .take(count)
+        .collect()
+}
+
+// Checks of the good dogs number them among the good dogs,
+// so this renumbers them by their positions in the file.
+fn in_file(e: GetDogsError, positions: &[usize]) -> GetDogsError {
+    match e {
+        GetDogsError::UnknownBreeds(mut unknown) => {
+            for u in &mut unknown {
+                u.index = positions[u.index];
+            }
+            GetDogsError::UnknownBreeds(unknown)
+        }
+        GetDogsError::Duplicates {
+            key,
+            mut duplicates,
+        } => {
+            for d in &mut duplicates {
+                d.first = positions[d.first];
+                d.second = positions[d.second];
+            }
+            GetDogsError::Duplicates { key, duplicates }
+        }
+        other => other,
+    }
+}
+
+// The dogs that a check of the good dogs found to be bad,
+// with a description of each.
+fn problem_dogs(e: &GetDogsError) -> Vec<(usize, String)> {
+    match e {
+        GetDogsError::UnknownBreeds(unknown) => {
+            unknown.iter().map(|u| (u.index, u.to_string())).collect()
+        }
+        // The first dog with a key is good and the others are duplicates of it.
+        GetDogsError::Duplicates { duplicates, .. } => duplicates
+            .iter()
+            .map(|d| (d.second, d.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn dedupe_dogs(
+    file: &str,
+    input: Option<FileFormat>,
+    key: UniqueKey,
+    strategy: DedupeStrategy,
+) -> Result<(), Failure> {
+    let dogs = get_dogs(file, input).with_context(|| format!("loading dogs from {}", file))?;
+    let before = dogs.len();
+    let dogs = dedupe(dogs, key, strategy);
+    println!("removed {} duplicate dogs", before - dogs.len());
+    if dogs.len() < before {
+        save(file, input, &dogs)?;
+    }
+    Ok(())
+}
+
+// Warnings go to stderr so stdout only has the command's output.
+fn convert(
+    file: &str,
+    input: Option<FileFormat>,
+    checks: &Checks,
+    output: &str,
+    output_format: Option<FileFormat>,
+) -> Result<(), Failure> {
+    let mut conversion =
+        read_for_conversion(file, input).with_context(|| format!("loading dogs from {}", file))?;
+    checks
+        .check(&mut conversion.dogs)
+        .with_context(|| format!("loading dogs from {}", file))?;
+    for warning in &conversion.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    save_dogs_as(output, &conversion.dogs, output_format)
+        .with_context(|| format!("saving dogs to {}", output))
+        .map_err(|e| Failure::Dogs(e, Some(output.to_string())))
+}
+
+fn stats(
+    file: &str,
+    input: Option<FileFormat>,
+    checks: &Checks,
+    format: Format,
+) -> Result<(), Failure> {
+    let dogs = load(file, input, checks)?;
+    let mut breeds: BTreeMap<&str, usize> = BTreeMap::new();
+    for dog in &dogs {
+        *breeds.entry(dog.breed()).or_default() += 1;
+    }
+    match format {
+        Format::Text => {
+            println!("{} dogs, {} breeds", dogs.len(), breeds.len());
+            for (breed, count) in &breeds {
+                println!("  {}: {}", breed, count);
+            }
+        }
+        Format::Json => {
+            print_json(&serde_json::json!({ "dogs": dogs.len(), "breeds": breeds }));
+        }
+        Format::Table => {
+            let counts: Vec<String> = breeds.values().map(|c| c.to_string()).collect();
+            let rows: Vec<[&str; 2]> = breeds
+                .keys()
+                .zip(&counts)
+                .map(|(breed, count)| [*breed, count.as_str()])
+                .collect();
+            print_table(["BREED", "COUNT"], &rows);
+        }
+    }
+    Ok(())
+}
+
+// These describe what was being done when loading or saving fails.
+fn load(file: &str, input: Option<FileFormat>, checks: &Checks) -> MyResult<Vec<Dog>> {
+    let mut dogs = get_dogs(file, input).with_context(|| format!("loading dogs from {}", file))?;
+    checks
+        .check(&mut dogs)
+        .with_context(|| format!("loading dogs from {}", file))?;
+    Ok(dogs)
+}
+
+fn save(file: &str, format: Option<FileFormat>, dogs: &[Dog]) -> Result<(), Failure> {
+    save_dogs_as(file, dogs, format)
+        .with_context(|| format!("saving dogs to {}", file))
+        .map_err(|e| Failure::Dogs(e, Some(file.to_string())))
+}
+
+fn print_json<T: serde::Serialize>(value: &T) {
+    // Serializing values built from strings and numbers cannot fail.
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+fn print_table(header: [&str; 2], rows: &[[&str; 2]]) {
+    let width = rows
+        .iter()
+        .map(|row| row[0].chars().count())
+        .chain(std::iter::once(header[0].len()))
+        .max()
+        .unwrap_or(0);
+    println!("{:width$}  {}", header[0], header[1], width = width);
+    for row in rows {
+        println!("{:width$}  {}", row[0], row[1], width = width);
+    }
+}
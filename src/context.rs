@@ -0,0 +1,78 @@
+use std::fmt;
+
+use crate::{GetDogsError, MyResult};
+
+/// Adds "while doing X" frames to the error in a [`MyResult`].
+///
+/// ```
+/// use rust_error_handling::{get_dogs3, Context};
+///
+/// let err = get_dogs3("missing.json")
+///     .context("loading kennel roster")
+///     .unwrap_err();
+/// assert!(err.to_string().starts_with("loading kennel roster: file not found: "));
+/// ```
+pub trait Context<T> {
+    /// Wraps the error with a description of what was being done.
+    fn context<C: fmt::Display>(self, context: C) -> MyResult<T>;
+
+    /// Like context, but the description is only built if there is an error.
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, f: F) -> MyResult<T>;
+}
+
+impl<T> Context<T> for MyResult<T> {
+    fn context<C: fmt::Display>(self, context: C) -> MyResult<T> {
+        self.map_err(|e| e.push_frame(context.to_string()))
+    }
+
+    fn with_context<C: fmt::Display, F: FnOnce() -> C>(self, f: F) -> MyResult<T> {
+        self.map_err(|e| e.push_frame(f().to_string()))
+    }
+}
+
+impl GetDogsError {
+    // Adding context to an error that already has some
+    // pushes another frame rather than nesting the errors,
+    // so the frames form a single stack.
+    fn push_frame(self, frame: String) -> Self {
+        match self {
+            GetDogsError::WithContext { mut frames, source } => {
+                frames.push(frame);
+                GetDogsError::WithContext { frames, source }
+            }
+            other => GetDogsError::WithContext {
+                frames: vec![frame],
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// Returns the error underneath any context frames,
+    /// or that of the last attempt of a retried operation.
+    pub fn root(&self) -> &GetDogsError {
+        match self {
+            GetDogsError::WithContext { source, .. } | GetDogsError::Retried { source, .. } => {
+                source.root()
+            }
+            other => other,
+        }
+    }
+}
+
+// The frames are stored innermost first, but are displayed outermost first.
+// The normal format puts the whole chain on one line and
+// the alternate format ({:#}) puts each part on its own line.
+pub(crate) fn fmt_frames(
+    f: &mut fmt::Formatter,
+    frames: &[String],
+    source: &GetDogsError,
+) -> fmt::Result {
+    let separator = if f.alternate() { "\ncaused by: " } else { ": " };
+    for (i, frame) in frames.iter().rev().enumerate() {
+        if i > 0 {
+            write!(f, "{}", separator)?;
+        }
+        write!(f, "{}", frame)?;
+    }
+    write!(f, "{}{}", separator, source)
+}
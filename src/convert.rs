@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+
+use crate::dog::FIELDS;
+use crate::encoding::decode_text;
+use crate::format::dogs_from_bytes;
+use crate::save::{to_json, write_atomically};
+use crate::validate::validate_dogs;
+use crate::{json, telemetry, Dog, FileFormat, FileSystem, GetDogsError, MyResult, RealFileSystem};
+
+/// A field in the input that [`Dog`] doesn't have,
+/// so converting the file drops it.
+/// The indices are the positions of the dogs that had a value for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConversionWarning {
+    pub field: String,
+    pub dogs: Vec<usize>,
+    /// True when the value is an object or array rather than a single value.
+    pub nested: bool,
+}
+
+impl fmt::Display for ConversionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.nested { "nested field" } else { "field" };
+        let dogs: Vec<String> = self.dogs.iter().map(|i| i.to_string()).collect();
+        write!(
+            f,
+            "{} {:?} is not a dog field and was dropped from dogs {}",
+            kind,
+            self.field,
+            dogs.join(", ")
+        )
+    }
+}
+
+/// The dogs read from a file along with what was lost reading them.
+#[derive(Debug)]
+pub struct Conversion {
+    pub dogs: Vec<Dog>,
+    pub warnings: Vec<ConversionWarning>,
+}
+
+/// Reads dogs like [`get_dogs`](crate::get_dogs),
+/// and also reports the fields in the file that dogs can't hold.
+pub fn read_for_conversion(file_path: &str, format: Option<FileFormat>) -> MyResult<Conversion> {
+    let format = FileFormat::resolve(format, file_path);
+    telemetry::traced(telemetry::load(file_path, format), || {
+        let bytes = RealFileSystem.read(file_path)?;
+        telemetry::record_bytes(bytes.len());
+        let dogs = dogs_from_bytes(&bytes, format)?;
+        telemetry::record_dogs(dogs.len());
+        validate_dogs(&dogs)?;
+        Ok(Conversion {
+            dogs,
+            warnings: dropped_fields(&bytes, format),
+        })
+    })
+}
+
+/// Writes dogs to a file in any supported format, replacing it atomically
+/// like [`save_dogs`](crate::save_dogs).
+///
+/// When format is None it is chosen from the file extension,
+/// and files with unrecognized extensions are written as JSON.
+/// Every format holds every field of a dog,
+/// which are written in the order they are declared.
+pub fn save_dogs_as(file_path: &str, dogs: &[Dog], format: Option<FileFormat>) -> MyResult<()> {
+    let format = FileFormat::resolve(format, file_path);
+    telemetry::traced(telemetry::save(file_path, format, dogs.len()), || {
+        write_atomically(file_path, &encode_dogs(dogs, format)?)
+    })
+}
+
+/// Reads dogs from one file and writes them to another,
+/// converting between formats.
+/// Formats are chosen like in [`get_dogs`](crate::get_dogs) and [`save_dogs_as`].
+///
+/// Fields in the input that dogs don't have are dropped
+/// and returned as warnings, rather than failing the conversion.
+pub fn convert_dogs(
+    input: &str,
+    input_format: Option<FileFormat>,
+    output: &str,
+    output_format: Option<FileFormat>,
+) -> MyResult<Vec<ConversionWarning>> {
+    let conversion = read_for_conversion(input, input_format)?;
+    save_dogs_as(output, &conversion.dogs, output_format)?;
+    Ok(conversion.warnings)
+}
+
+#[derive(Serialize)]
+struct TomlDogs<'a> {
+    dogs: &'a [Dog],
+}
+
+fn encode_dogs(dogs: &[Dog], format: FileFormat) -> MyResult<Vec<u8>> {
+    let cannot_encode = |e: Box<dyn std::error::Error + Send + Sync>| GetDogsError::CannotEncode {
+        format,
+        source: e,
+    };
+    match format {
+        // JSON files use the versioned format of save_dogs.
+        FileFormat::Json => to_json(dogs).map_err(GetDogsError::CannotSerialize),
+        FileFormat::Ndjson => {
+            let mut ndjson = Vec::new();
+            for dog in dogs {
+                serde_json::to_writer(&mut ndjson, dog).map_err(GetDogsError::CannotSerialize)?;
+                ndjson.push(b'\n');
+            }
+            Ok(ndjson)
+        }
+        FileFormat::Yaml => serde_yaml::to_string(dogs)
+            .map(String::into_bytes)
+            .map_err(|e| cannot_encode(e.into())),
+        FileFormat::Toml => toml::to_string(&TomlDogs { dogs })
+            .map(String::into_bytes)
+            .map_err(|e| cannot_encode(e.into())),
+        FileFormat::Csv => to_csv(dogs).map_err(|e| cannot_encode(e.into())),
+        #[cfg(feature = "msgpack")]
+        FileFormat::MessagePack => crate::binary::msgpack_to_vec(dogs),
+        #[cfg(feature = "cbor")]
+        FileFormat::Cbor => crate::binary::cbor_to_vec(dogs),
+    }
+}
+
+// The csv crate requires every row to have the same columns,
+// but dogs leave out the optional fields they don't have.
+// So the columns are the fields that any dog has,
+// and missing values are written as empty cells, which read back as None.
+fn to_csv(dogs: &[Dog]) -> csv::Result<Vec<u8>> {
+    let rows: Vec<Map<String, Value>> = dogs
+        .iter()
+        .map(|dog| match serde_json::to_value(dog) {
+            Ok(Value::Object(map)) => map,
+            _ => unreachable!("dogs serialize as objects"),
+        })
+        .collect();
+    // The name and breed are always written, even when there are no dogs.
+    let columns: Vec<&str> = FIELDS
+        .iter()
+        .enumerate()
+        .filter(|&(i, field)| i < 2 || rows.iter().any(|row| row.contains_key(*field)))
+        .map(|(_, field)| *field)
+        .collect();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(&columns)?;
+    for row in &rows {
+        writer.write_record(columns.iter().map(|column| match row.get(*column) {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(other) => other.to_string(),
+        }))?;
+    }
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+#[derive(Deserialize)]
+struct TomlValues {
+    dogs: Vec<Value>,
+}
+
+// This reads the file again as plain values to find the fields
+// that were ignored when it was read as dogs.
+// The file has already been read as dogs, so this doesn't fail,
+// but if it did the warnings would only be incomplete.
+fn dropped_fields(bytes: &[u8], format: FileFormat) -> Vec<ConversionWarning> {
+    let mut warnings: Vec<ConversionWarning> = Vec::new();
+    for (index, value) in values_from_bytes(bytes, format).into_iter().enumerate() {
+        let object = match value {
+            Value::Object(object) => object,
+            _ => continue,
+        };
+        for (field, value) in object {
+            if FIELDS.contains(&field.as_str()) || value.is_null() {
+                continue;
+            }
+            let nested = value.is_object() || value.is_array();
+            match warnings.iter_mut().find(|w| w.field == field) {
+                Some(warning) => {
+                    warning.dogs.push(index);
+                    warning.nested |= nested;
+                }
+                None => warnings.push(ConversionWarning {
+                    field,
+                    dogs: vec![index],
+                    nested,
+                }),
+            }
+        }
+    }
+    warnings
+}
+
+fn values_from_bytes(bytes: &[u8], format: FileFormat) -> Vec<Value> {
+    let text = || decode_text(bytes).unwrap_or_default();
+    let values = match format {
+        FileFormat::Json => json::dog_values_from_str(&text()).ok(),
+        FileFormat::Ndjson => {
+            let text = text();
+            if text.trim_start().starts_with('[') {
+                serde_json::from_str(&text).ok()
+            } else {
+                text.lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(serde_json::from_str)
+                    .collect::<Result<_, _>>()
+                    .ok()
+            }
+        }
+        FileFormat::Yaml => serde_yaml::from_str(&text()).ok(),
+        FileFormat::Toml => toml::from_str::<TomlValues>(&text())
+            .ok()
+            .map(|doc| doc.dogs),
+        FileFormat::Csv => csv_values(&text()).ok(),
+        #[cfg(feature = "msgpack")]
+        FileFormat::MessagePack => rmp_serde::from_slice(bytes).ok(),
+        #[cfg(feature = "cbor")]
+        FileFormat::Cbor => ciborium::from_reader(bytes).ok(),
+    };
+    values.unwrap_or_default()
+}
+
+// CSV cells are all text, and empty cells are missing values.
+fn csv_values(text: &str) -> csv::Result<Vec<Value>> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    reader
+        .records()
+        .map(|record| {
+            let record = record?;
+            Ok(Value::Object(
+                headers
+                    .iter()
+                    .zip(record.iter())
+                    .filter(|(_, cell)| !cell.is_empty())
+                    .map(|(header, cell)| (header.to_string(), Value::String(cell.to_string())))
+                    .collect(),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,69 @@
+use std::fmt::Write;
+
+// The number of lines shown before the line where parsing failed.
+// A missing comma is usually reported on the line after it,
+// so showing a little context helps find the real mistake.
+const CONTEXT_LINES: usize = 2;
+
+/// Renders a serde_json error in the style of rustc diagnostics.
+///
+/// It names the file, shows the lines surrounding the failure,
+/// and places a caret under the column where parsing stopped.
+/// For example:
+///
+/// ```text
+/// error: bad JSON: expected `,` or `}` at line 4 column 9
+///  --> ./dogs.json:4:9
+///   |
+/// 2 |     {
+/// 3 |         "name": "Comet"
+/// 4 |         "breed": "Whippet"
+///   |         ^
+/// ```
+pub fn render_json_error(file_path: &str, source: &str, err: &serde_json::Error) -> String {
+    let mut out = String::new();
+    // Writing to a String cannot fail, so the results are ignored.
+    let _ = writeln!(out, "error: bad JSON: {}", err);
+
+    // serde_json reports line 0 when the error
+    // is not associated with a position in the input.
+    let line = err.line();
+    if line == 0 {
+        let _ = writeln!(out, " --> {}", file_path);
+        return out;
+    }
+    let column = err.column();
+    let _ = writeln!(out, " --> {}:{}:{}", file_path, line, column);
+
+    let lines: Vec<&str> = source.lines().collect();
+    // Errors at the end of the input can point one line past the last one.
+    let last = line.min(lines.len().max(1));
+    let first = last.saturating_sub(CONTEXT_LINES).max(1);
+    let width = last.to_string().len();
+
+    let _ = writeln!(out, "{:width$} |", "", width = width);
+    for number in first..=last {
+        let text = lines.get(number - 1).copied().unwrap_or("");
+        let _ = writeln!(out, "{:>width$} | {}", number, text, width = width);
+    }
+    let text = lines.get(last - 1).copied().unwrap_or("");
+    let _ = writeln!(
+        out,
+        "{:width$} | {}^",
+        "",
+        caret_padding(text, column),
+        width = width
+    );
+    out
+}
+
+// This returns the whitespace that places a caret under the given
+// 1-based column of a line. serde_json counts columns in bytes,
+// so multi-byte characters take a single space and
+// tabs are preserved so the caret lines up in a terminal.
+fn caret_padding(text: &str, column: usize) -> String {
+    text.char_indices()
+        .take_while(|(i, _)| i + 1 < column)
+        .map(|(_, c)| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
@@ -0,0 +1,307 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A dog described by an element of the JSON array.
+///
+/// Only the name and breed are required.
+/// The other fields default to None when missing from a file
+/// and are left out when saving if they are None,
+/// so files with only names and breeds keep working.
+// This struct can be deserialized from JSON and serialized to JSON.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Dog {
+    pub(crate) name: String,
+    pub(crate) breed: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    birth_date: Option<Date>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sex: Option<Sex>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_weight",
+        skip_serializing_if = "Option::is_none"
+    )]
+    weight: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_microchip",
+        skip_serializing_if = "Option::is_none"
+    )]
+    microchip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+}
+
+// The names of the fields in the order they are declared,
+// which is the order they are written in.
+// This must be kept in sync with the struct.
+pub(crate) const FIELDS: [&str; 9] = [
+    "name",
+    "breed",
+    "birth_date",
+    "sex",
+    "weight",
+    "color",
+    "microchip",
+    "owner",
+    "notes",
+];
+
+impl Dog {
+    /// Creates a dog. It is not validated until passed to [`validate_dog`].
+    ///
+    /// The optional fields can be set with the `with_` methods.
+    ///
+    /// [`validate_dog`]: crate::validate_dog
+    pub fn new(name: impl Into<String>, breed: impl Into<String>) -> Self {
+        Dog {
+            name: name.into(),
+            breed: breed.into(),
+            birth_date: None,
+            sex: None,
+            weight: None,
+            color: None,
+            microchip: None,
+            owner: None,
+            notes: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn breed(&self) -> &str {
+        &self.breed
+    }
+
+    pub fn birth_date(&self) -> Option<Date> {
+        self.birth_date
+    }
+
+    pub fn sex(&self) -> Option<Sex> {
+        self.sex
+    }
+
+    /// The weight in kilograms.
+    pub fn weight(&self) -> Option<f64> {
+        self.weight
+    }
+
+    pub fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    /// The 15-digit ISO 11784 microchip number.
+    pub fn microchip(&self) -> Option<&str> {
+        self.microchip.as_deref()
+    }
+
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
+    }
+
+    pub fn with_birth_date(mut self, birth_date: Date) -> Self {
+        self.birth_date = Some(birth_date);
+        self
+    }
+
+    pub fn with_sex(mut self, sex: Sex) -> Self {
+        self.sex = Some(sex);
+        self
+    }
+
+    /// Sets the weight in kilograms.
+    /// Negative weights are rejected when the dog is loaded again,
+    /// so check them with [`check_weight`] first.
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn with_color(mut self, color: impl Into<String>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    /// Sets the microchip number. It should be checked with
+    /// [`check_microchip`] for the same reason as weights.
+    pub fn with_microchip(mut self, microchip: impl Into<String>) -> Self {
+        self.microchip = Some(microchip.into());
+        self
+    }
+
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
+    pub fn with_notes(mut self, notes: impl Into<String>) -> Self {
+        self.notes = Some(notes.into());
+        self
+    }
+
+    // This is used to merge duplicate dogs.
+    // Fields that are already set are left alone.
+    pub(crate) fn fill_missing(&mut self, other: &Dog) {
+        fn fill<T: Clone>(field: &mut Option<T>, other: &Option<T>) {
+            if field.is_none() {
+                *field = other.clone();
+            }
+        }
+        fill(&mut self.birth_date, &other.birth_date);
+        fill(&mut self.sex, &other.sex);
+        fill(&mut self.weight, &other.weight);
+        fill(&mut self.color, &other.color);
+        fill(&mut self.microchip, &other.microchip);
+        fill(&mut self.owner, &other.owner);
+        fill(&mut self.notes, &other.notes);
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Sex {
+    Male,
+    Female,
+}
+
+/// A calendar date written as YYYY-MM-DD in files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    /// Returns an error message if the month or day is out of range.
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Self, String> {
+        if !(1..=12).contains(&month) {
+            return Err(format!("month {} is not between 1 and 12", month));
+        }
+        let leap =
+            year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+        let days = match month {
+            2 if leap => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        if day < 1 || day > days {
+            return Err(format!("day {} is not between 1 and {}", day, days));
+        }
+        Ok(Date { year, month, day })
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('-').collect();
+        let number = |i: usize, len: usize| match parts.get(i) {
+            Some(part) if part.len() == len && part.bytes().all(|b| b.is_ascii_digit()) => {
+                part.parse().ok()
+            }
+            _ => None,
+        };
+        match (parts.len(), number(0, 4), number(1, 2), number(2, 2)) {
+            (3, Some(year), Some(month), Some(day)) => Date::new(year, month as u8, day as u8),
+            _ => Err(format!("{:?} is not a date in the form YYYY-MM-DD", s)),
+        }
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DateVisitor;
+
+        impl<'de> Visitor<'de> for DateVisitor {
+            type Value = Date;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a date in the form YYYY-MM-DD")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Date, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(DateVisitor)
+    }
+}
+
+/// Returns an error message if a weight can't be right.
+pub fn check_weight(weight: f64) -> Result<f64, String> {
+    if !weight.is_finite() || weight < 0.0 {
+        Err(format!("weight {} is not a non-negative number", weight))
+    } else {
+        Ok(weight)
+    }
+}
+
+/// Returns an error message if a microchip number isn't 15 digits.
+pub fn check_microchip(microchip: &str) -> Result<&str, String> {
+    if microchip.len() == 15 && microchip.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(microchip)
+    } else {
+        Err(format!("microchip {:?} is not 15 digits", microchip))
+    }
+}
+
+// These check values while they are deserialized
+// so the error points at the field in the file.
+fn deserialize_weight<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    match Option::<f64>::deserialize(deserializer)? {
+        Some(weight) => check_weight(weight).map(Some).map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+fn deserialize_microchip<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(microchip) => match check_microchip(&microchip) {
+            Ok(_) => Ok(Some(microchip)),
+            Err(message) => Err(de::Error::custom(message)),
+        },
+        None => Ok(None),
+    }
+}
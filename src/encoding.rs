@@ -0,0 +1,84 @@
+use crate::{telemetry, FileSystem, GetDogsError, MyResult};
+
+/// Reads a text file the way the loaders do.
+///
+/// Files can be UTF-8 or UTF-16, with or without a byte order mark.
+/// Windows tools often write UTF-16 with a byte order mark,
+/// and UTF-16 without one is recognized because dogs files
+/// start with an ASCII character, which has a zero byte in UTF-16.
+///
+/// Failures are reported as the specific variants NotFound,
+/// PermissionDenied, Encoding and Empty where they apply,
+/// and as BadFile for other I/O errors.
+pub fn read_text(fs: &dyn FileSystem, file_path: &str) -> MyResult<String> {
+    let bytes = fs.read(file_path)?;
+    telemetry::record_bytes(bytes.len());
+    decode_text(&bytes)
+}
+
+// This is shared with the async loader, which reads the bytes itself.
+pub(crate) fn decode_text(bytes: &[u8]) -> MyResult<String> {
+    let text = decode(bytes)?;
+    if text.trim().is_empty() {
+        return Err(GetDogsError::Empty);
+    }
+    Ok(text)
+}
+
+fn decode(bytes: &[u8]) -> MyResult<String> {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, ..] => decode_utf8(bytes, 3),
+        [0xFF, 0xFE, ..] => decode_utf16(bytes, 2, u16::from_le_bytes, "UTF-16LE"),
+        [0xFE, 0xFF, ..] => decode_utf16(bytes, 2, u16::from_be_bytes, "UTF-16BE"),
+        [b, 0, ..] if *b != 0 => decode_utf16(bytes, 0, u16::from_le_bytes, "UTF-16LE"),
+        [0, b, ..] if *b != 0 => decode_utf16(bytes, 0, u16::from_be_bytes, "UTF-16BE"),
+        _ => decode_utf8(bytes, 0),
+    }
+}
+
+// Offsets in errors count from the start of the file,
+// including any byte order mark.
+fn decode_utf8(bytes: &[u8], start: usize) -> MyResult<String> {
+    match std::str::from_utf8(&bytes[start..]) {
+        Ok(text) => Ok(text.to_string()),
+        Err(e) => Err(GetDogsError::Encoding {
+            offset: start + e.valid_up_to(),
+            encoding: "UTF-8",
+        }),
+    }
+}
+
+fn decode_utf16(
+    bytes: &[u8],
+    start: usize,
+    unit: fn([u8; 2]) -> u16,
+    encoding: &'static str,
+) -> MyResult<String> {
+    let body = &bytes[start..];
+    let units = body.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    let mut text = String::with_capacity(body.len() / 2);
+    // This counts code units so an unpaired surrogate can be located.
+    let mut decoded = 0;
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => {
+                text.push(c);
+                decoded += c.len_utf16();
+            }
+            Err(_) => {
+                return Err(GetDogsError::Encoding {
+                    offset: start + decoded * 2,
+                    encoding,
+                })
+            }
+        }
+    }
+    // An odd number of bytes leaves half a code unit at the end.
+    if body.len() % 2 == 1 {
+        return Err(GetDogsError::Encoding {
+            offset: bytes.len() - 1,
+            encoding,
+        });
+    }
+    Ok(text)
+}
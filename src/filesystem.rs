@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, ErrorKind, Read};
+
+/// Where the loaders read files from.
+///
+/// The loaders use [`RealFileSystem`] unless given another implementation,
+/// such as a [`FaultyFileSystem`] that makes reads fail on purpose
+/// so error handling can be tested without touching the disk.
+pub trait FileSystem {
+    /// Opens a file for reading.
+    fn open(&self, path: &str) -> io::Result<Box<dyn Read + '_>>;
+
+    /// Reads a whole file.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        self.open(path)?.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Reads a whole file, which must be UTF-8.
+    /// Like [`std::fs::read_to_string`], reads that are
+    /// interrupted are retried and invalid UTF-8 is an
+    /// io::Error with the kind InvalidData.
+    fn read_to_string(&self, path: &str) -> io::Result<String> {
+        let mut contents = String::new();
+        self.open(path)?.read_to_string(&mut contents)?;
+        Ok(contents)
+    }
+}
+
+/// The operating system's file system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn open(&self, path: &str) -> io::Result<Box<dyn Read + '_>> {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+/// A failure that a [`FaultyFileSystem`] injects when a file is read.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fault {
+    /// Opening the file fails as if it doesn't exist.
+    NotFound,
+    /// Opening the file fails as if it can't be read by this user.
+    PermissionDenied,
+    /// Every other read is interrupted and each read returns only a few bytes.
+    /// Readers that retry interrupted reads still get the whole file.
+    Interrupted,
+    /// The byte at this offset is replaced by one that isn't valid UTF-8,
+    /// or one is appended if the file is shorter.
+    InvalidUtf8(usize),
+    /// Only this many bytes can be read, as if the file was cut short.
+    Truncated(usize),
+    /// Reads fail with this kind of error once this many bytes have been read,
+    /// as if a network volume went away.
+    FailAfter(usize, ErrorKind),
+}
+
+/// An in-memory file system that injects [`Fault`]s.
+///
+/// ```
+/// # use rust_error_handling::{get_dogs3_from, Fault, FaultyFileSystem, GetDogsError};
+/// let fs = FaultyFileSystem::new()
+///     .with_file("dogs.json", r#"[{"name": "Comet", "breed": "Whippet"}]"#)
+///     .with_fault("dogs.json", Fault::Truncated(10));
+/// assert!(matches!(
+///     get_dogs3_from(&fs, "dogs.json"),
+///     Err(GetDogsError::BadJson(_))
+/// ));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FaultyFileSystem {
+    files: HashMap<String, Vec<u8>>,
+    faults: HashMap<String, Vec<Fault>>,
+}
+
+impl FaultyFileSystem {
+    /// Creates a file system with no files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file. Reading a path that wasn't added fails with NotFound.
+    pub fn with_file(mut self, path: &str, contents: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(path.to_string(), contents.into());
+        self
+    }
+
+    /// Adds a fault to the reads of a path. A path can have several.
+    pub fn with_fault(mut self, path: &str, fault: Fault) -> Self {
+        self.faults.entry(path.to_string()).or_default().push(fault);
+        self
+    }
+}
+
+impl FileSystem for FaultyFileSystem {
+    fn open(&self, path: &str) -> io::Result<Box<dyn Read + '_>> {
+        let faults = self.faults.get(path).map(Vec::as_slice).unwrap_or(&[]);
+        let mut reader = FaultyReader::default();
+        for fault in faults {
+            match *fault {
+                Fault::NotFound => return Err(ErrorKind::NotFound.into()),
+                Fault::PermissionDenied => return Err(ErrorKind::PermissionDenied.into()),
+                _ => {}
+            }
+        }
+        reader.contents = match self.files.get(path) {
+            Some(contents) => contents.clone(),
+            None => return Err(ErrorKind::NotFound.into()),
+        };
+        for fault in faults {
+            match *fault {
+                Fault::Interrupted => reader.interrupt = true,
+                Fault::InvalidUtf8(offset) => match reader.contents.get_mut(offset) {
+                    Some(byte) => *byte = 0xFF,
+                    None => reader.contents.push(0xFF),
+                },
+                Fault::Truncated(len) => reader.contents.truncate(len),
+                Fault::FailAfter(len, kind) => reader.fail_after = Some((len, kind)),
+                Fault::NotFound | Fault::PermissionDenied => {}
+            }
+        }
+        Ok(Box::new(reader))
+    }
+}
+
+#[derive(Default)]
+struct FaultyReader {
+    contents: Vec<u8>,
+    position: usize,
+    interrupt: bool,
+    interrupted: bool,
+    fail_after: Option<(usize, ErrorKind)>,
+}
+
+impl Read for FaultyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some((len, kind)) = self.fail_after {
+            if self.position >= len {
+                return Err(io::Error::new(kind, "injected fault"));
+            }
+        }
+        let mut end = self.contents.len();
+        if self.interrupt {
+            self.interrupted = !self.interrupted;
+            if self.interrupted {
+                return Err(ErrorKind::Interrupted.into());
+            }
+            end = end.min(self.position + 8);
+        }
+        if let Some((len, _)) = self.fail_after {
+            end = end.min(len);
+        }
+        let count = (end - self.position).min(buf.len());
+        buf[..count].copy_from_slice(&self.contents[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
@@ -0,0 +1,131 @@
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::encoding::decode_text;
+use crate::validate::validate_dogs;
+use crate::{json, telemetry, Dog, DogStream, FileSystem, GetDogsError, MyResult, RealFileSystem};
+
+/// The file formats that dogs can be read from and written to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileFormat {
+    Json,
+    /// Newline-delimited JSON with one dog per line.
+    Ndjson,
+    Yaml,
+    Toml,
+    Csv,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl FileFormat {
+    /// Chooses a format from the extension of a file path.
+    /// Returns None when the extension isn't recognized.
+    pub fn from_path(file_path: &str) -> Option<Self> {
+        let extension = Path::new(file_path).extension()?.to_str()?;
+        extension.parse().ok()
+    }
+
+    // An explicit format wins, then the extension, and JSON is the default.
+    pub(crate) fn resolve(format: Option<Self>, file_path: &str) -> Self {
+        format
+            .or_else(|| FileFormat::from_path(file_path))
+            .unwrap_or(FileFormat::Json)
+    }
+}
+
+// This accepts the same names as the file extensions,
+// which enables "--input-format yaml" on the command line.
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(FileFormat::Json),
+            "ndjson" | "jsonl" => Ok(FileFormat::Ndjson),
+            "yaml" | "yml" => Ok(FileFormat::Yaml),
+            "toml" => Ok(FileFormat::Toml),
+            "csv" => Ok(FileFormat::Csv),
+            #[cfg(feature = "msgpack")]
+            "msgpack" | "mpk" => Ok(FileFormat::MessagePack),
+            #[cfg(feature = "cbor")]
+            "cbor" => Ok(FileFormat::Cbor),
+            _ => Err(format!("unknown file format {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FileFormat::Json => "json",
+            FileFormat::Ndjson => "ndjson",
+            FileFormat::Yaml => "yaml",
+            FileFormat::Toml => "toml",
+            FileFormat::Csv => "csv",
+            #[cfg(feature = "msgpack")]
+            FileFormat::MessagePack => "msgpack",
+            #[cfg(feature = "cbor")]
+            FileFormat::Cbor => "cbor",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// TOML documents must be tables, so the dogs are
+// stored in an array of tables like this:
+//
+// [[dogs]]
+// name = "Comet"
+// breed = "Whippet"
+#[derive(Deserialize)]
+struct TomlDogs {
+    dogs: Vec<Dog>,
+}
+
+/// Reads dogs from a file in any supported format and validates them.
+///
+/// When format is None it is chosen from the file extension,
+/// and files with unrecognized extensions are read as JSON.
+/// Parse failures are reported with a variant for each format
+/// so callers can tell which parser rejected the file.
+pub fn get_dogs(file_path: &str, format: Option<FileFormat>) -> MyResult<Vec<Dog>> {
+    let format = FileFormat::resolve(format, file_path);
+    telemetry::traced(telemetry::load(file_path, format), || {
+        let bytes = RealFileSystem.read(file_path)?;
+        telemetry::record_bytes(bytes.len());
+        let dogs = dogs_from_bytes(&bytes, format)?;
+        telemetry::record_dogs(dogs.len());
+        validate_dogs(&dogs)?;
+        Ok(dogs)
+    })
+}
+
+// Text formats are decoded first so they can be UTF-16 like JSON files.
+pub(crate) fn dogs_from_bytes(bytes: &[u8], format: FileFormat) -> MyResult<Vec<Dog>> {
+    let text = || decode_text(bytes);
+    match format {
+        FileFormat::Json => json::dogs_from_str(&text()?),
+        // The stream validates each dog as it goes,
+        // so an invalid dog is reported with its line's index.
+        FileFormat::Ndjson => DogStream::untraced(text()?.as_bytes()).collect(),
+        FileFormat::Yaml => serde_yaml::from_str(&text()?).map_err(GetDogsError::BadYaml),
+        FileFormat::Toml => toml::from_str::<TomlDogs>(&text()?)
+            .map(|doc| doc.dogs)
+            .map_err(GetDogsError::BadToml),
+        // The first row of a CSV file must be a header
+        // that names the columns, such as "name,breed".
+        FileFormat::Csv => csv::Reader::from_reader(text()?.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<Dog>, _>>()
+            .map_err(GetDogsError::BadCsv),
+        #[cfg(feature = "msgpack")]
+        FileFormat::MessagePack => crate::binary::msgpack_from_slice(bytes),
+        #[cfg(feature = "cbor")]
+        FileFormat::Cbor => crate::binary::cbor_from_slice(bytes),
+    }
+}
@@ -0,0 +1,115 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use serde_path_to_error::{Path, Segment};
+
+use crate::version::{dogs_of, upgrade, version_of, CURRENT_VERSION};
+use crate::{Dog, GetDogsError, MyResult};
+
+// These parse dogs from JSON like serde_json does,
+// but track where in the document a failure occurred.
+// A bad value in a field, such as a date that doesn't exist,
+// is reported as BadField so callers know which dog and field to fix.
+// Syntax errors and missing fields are still reported as BadJson.
+
+// The version field is ignored once a document has been upgraded.
+#[derive(Deserialize)]
+struct Envelope {
+    dogs: Vec<Dog>,
+}
+
+// This accepts documents of any supported version
+// and upgrades older ones with the registered migrations.
+pub(crate) fn dogs_from_str(json: &str) -> MyResult<Vec<Dog>> {
+    let doc: Value = serde_json::from_str(json)?;
+    let bare = doc.is_array();
+    if let Some(version) = version_of(&doc)? {
+        if version < CURRENT_VERSION {
+            let original = dogs_of(&doc).cloned();
+            let upgraded = upgrade(doc, version)?;
+            // When the migrations didn't change any dogs, they are parsed
+            // from the text below so errors keep their line and column.
+            if dogs_of(&upgraded) != original.as_ref() {
+                return deserialize(upgraded).map(|e: Envelope| e.dogs);
+            }
+        }
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let dogs = if bare {
+        deserialize(&mut deserializer)?
+    } else {
+        deserialize(&mut deserializer).map(|e: Envelope| e.dogs)?
+    };
+    deserializer.end()?;
+    Ok(dogs)
+}
+
+// This returns the array of dogs in a document as generic values,
+// after upgrading it to the current version.
+pub(crate) fn dog_values_from_str(json: &str) -> MyResult<Vec<Value>> {
+    let mut doc: Value = serde_json::from_str(json)?;
+    if let Some(version) = version_of(&doc)? {
+        doc = upgrade(doc, version)?;
+    }
+    let dogs = match doc {
+        Value::Object(mut object) => object.remove("dogs").unwrap_or(Value::Null),
+        other => other,
+    };
+    Ok(serde_json::from_value(dogs)?)
+}
+
+fn deserialize<'de, D, T>(deserializer: D) -> MyResult<T>
+where
+    D: serde::Deserializer<'de, Error = serde_json::Error>,
+    T: DeserializeOwned,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|e| classify(None, e))
+}
+
+// This parses a single dog that is at the given index in an array.
+pub(crate) fn dog_from_slice(index: usize, json: &[u8]) -> MyResult<Dog> {
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let dog = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|e| classify(Some(index), e))?;
+    deserializer.end()?;
+    Ok(dog)
+}
+
+pub(crate) fn dog_from_value(index: usize, value: serde_json::Value) -> MyResult<Dog> {
+    serde_path_to_error::deserialize(value).map_err(|e| classify(Some(index), e))
+}
+
+fn classify(
+    index: Option<usize>,
+    err: serde_path_to_error::Error<serde_json::Error>,
+) -> GetDogsError {
+    let path = err.path().clone();
+    let source = err.into_inner();
+    if !source.is_data() {
+        return GetDogsError::BadJson(source);
+    }
+    match field_at(index, &path) {
+        Some((index, field)) => GetDogsError::BadField {
+            index,
+            field,
+            source,
+        },
+        None => GetDogsError::BadJson(source),
+    }
+}
+
+// The path is [index].field when parsing an array of dogs,
+// dogs[index].field when parsing a versioned document
+// and just .field when parsing one dog.
+fn field_at(index: Option<usize>, path: &Path) -> Option<(usize, String)> {
+    let segments: Vec<&Segment> = path.iter().collect();
+    match (index, segments.as_slice()) {
+        (None, [Segment::Seq { index }, Segment::Map { key }])
+        | (None, [Segment::Map { .. }, Segment::Seq { index }, Segment::Map { key }]) => {
+            Some((*index, key.clone()))
+        }
+        (Some(index), [Segment::Map { key }]) => Some((index, key.clone())),
+        _ => None,
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+
+use crate::validate::validate_dog;
+use crate::{get_dogs3, json, read_text, telemetry, Dog, GetDogsError, MyResult, RealFileSystem};
+
+/// This selects what happens when some dogs in a file are bad.
+/// Strict mode fails on the first bad dog, just like get_dogs3.
+/// Lenient mode keeps the good dogs and collects an error for each bad one
+/// so a large file can be triaged in a single pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Strict,
+    Lenient,
+}
+
+/// This is an error for one element of the JSON array.
+/// The index is the position of the element in the array.
+#[derive(Debug)]
+pub struct ElementError {
+    pub index: usize,
+    pub error: GetDogsError,
+}
+
+impl fmt::Display for ElementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dog {}: {}", self.index, self.error)
+    }
+}
+
+/// This is what load_dogs returns when the file as a whole was usable.
+/// In strict mode errors is always empty.
+#[derive(Debug)]
+pub struct Loaded {
+    pub dogs: Vec<Dog>,
+    pub errors: Vec<ElementError>,
+}
+
+/// Errors that affect the whole file, such as the file not being readable
+/// or the JSON not being an array, are returned as an Err in both modes.
+pub fn load_dogs(file_path: &str, mode: Mode) -> MyResult<Loaded> {
+    if mode == Mode::Strict {
+        return Ok(Loaded {
+            dogs: get_dogs3(file_path)?,
+            errors: Vec::new(),
+        });
+    }
+
+    telemetry::traced(telemetry::load(file_path, "json"), || {
+        load_lenient(file_path)
+    })
+}
+
+fn load_lenient(file_path: &str) -> MyResult<Loaded> {
+    let json = read_text(&RealFileSystem, file_path)?;
+    // Parsing into generic values first means that one
+    // malformed dog doesn't prevent reading the others.
+    let values = json::dog_values_from_str(&json)?;
+    telemetry::record_dogs(values.len());
+
+    let mut loaded = Loaded {
+        dogs: Vec::new(),
+        errors: Vec::new(),
+    };
+    for (index, value) in values.into_iter().enumerate() {
+        match json::dog_from_value(index, value) {
+            Ok(dog) => {
+                let violations = validate_dog(index, &dog);
+                if violations.is_empty() {
+                    loaded.dogs.push(dog);
+                } else {
+                    loaded.errors.push(ElementError {
+                        index,
+                        error: GetDogsError::Invalid(violations),
+                    });
+                }
+            }
+            Err(error) => loaded.errors.push(ElementError { index, error }),
+        }
+    }
+    for e in &loaded.errors {
+        telemetry::error_event(&e.error, Some(e.index));
+    }
+    Ok(loaded)
+}
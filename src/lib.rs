@@ -0,0 +1,592 @@
+//! This demonstrates several ways of handling errors
+//! in functions that return multiple error types.
+//!
+//! The functions [`get_dogs1`], [`get_dogs2`] and [`get_dogs3`]
+//! read a JSON file describing dogs and compare approaches.
+//! [`load_dogs`] adds a lenient mode that collects every bad dog
+//! and [`save_dogs`] writes them back.
+//! [`get_dogs`] also reads NDJSON, YAML, TOML and CSV files,
+//! and MessagePack and CBOR files with the `msgpack` and `cbor` features.
+//! [`convert_dogs`] converts between them.
+//! Each loader has a `_from` version that reads from a [`FileSystem`],
+//! which can be a [`FaultyFileSystem`] that injects read failures.
+//! [`stream_dogs`] reads one dog at a time from files too large for memory.
+//! [`RetryPolicy`] retries loaders that fail with transient errors,
+//! such as reads of network file systems that time out.
+//! [`check_unique`] and [`UniqueStore`] reject duplicate dogs
+//! and [`dedupe`] merges them.
+//! [`Query`] filters, sorts and limits dogs using a small query language.
+//! [`BreedRegistry`] turns breed aliases into canonical names
+//! and suggests the closest breed for ones it doesn't know.
+//! The [`DogStore`] trait keeps dogs in a JSON file, in memory or in SQLite.
+//! With the `async` feature, `get_dogs_async` and `save_dogs_async`
+//! load and save on a tokio runtime without blocking it.
+//! [`ProblemDetails`] describes errors in HTTP responses for the `dog-server` binary.
+//! Loads and saves are traced with the `tracing` crate, recording
+//! their path, format, size, dog count and duration, and every error they return.
+//! With the `metrics` feature, `Metrics` counts them and their failures
+//! by error variant for Prometheus.
+
+use std::error::Error;
+use std::fmt;
+
+mod dog;
+mod encoding;
+pub use encoding::read_text;
+mod filesystem;
+pub use dog::{check_microchip, check_weight, Date, Dog, Sex};
+pub use filesystem::{Fault, FaultyFileSystem, FileSystem, RealFileSystem};
+mod diagnostic;
+mod json;
+mod version;
+pub use diagnostic::render_json_error;
+pub use version::{migrations, Migration, CURRENT_VERSION};
+mod validate;
+pub use validate::{validate_dog, validate_dogs, Problem, Violation};
+mod lenient;
+pub use lenient::{load_dogs, ElementError, Loaded, Mode};
+mod save;
+pub use save::save_dogs;
+mod format;
+pub use format::{get_dogs, FileFormat};
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod binary;
+#[cfg(feature = "cbor")]
+pub use binary::{get_dogs_cbor, get_dogs_cbor_from, save_dogs_cbor};
+#[cfg(feature = "msgpack")]
+pub use binary::{get_dogs_msgpack, get_dogs_msgpack_from, save_dogs_msgpack};
+mod convert;
+pub use convert::{convert_dogs, read_for_conversion, save_dogs_as, Conversion, ConversionWarning};
+mod stream;
+pub use stream::{stream_dogs, DogStream, SyntaxProblem};
+mod context;
+pub use context::Context;
+mod report;
+pub use report::{ErrorReport, ViolationReport};
+mod problem;
+pub use problem::ProblemDetails;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod telemetry;
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+#[cfg(feature = "async")]
+mod async_io;
+#[cfg(feature = "async")]
+pub use async_io::{cancellable, get_dogs_async, save_dogs_async, timeout, CancelToken};
+mod breeds;
+mod query;
+pub use query::{query_dogs, Query, QueryError};
+mod retry;
+mod store;
+mod unique;
+pub use breeds::{canonicalize_breeds, BreedMode, BreedRegistry, UnknownBreed};
+pub use retry::RetryPolicy;
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
+pub use store::{DogStore, JsonFileStore, MemoryStore, StoreProblem};
+pub use unique::{
+    check_unique, dedupe, find_duplicates, DedupeStrategy, Duplicate, UniqueKey, UniqueStore,
+};
+
+/// The ways in which loading or saving dogs can fail.
+///
+/// This is a custom error type.
+/// It enables callers that receive this kind of error
+/// to handle different error causes differently.
+/// It is marked `non_exhaustive` so new variants can be added
+/// without breaking callers, who must include a wildcard match arm.
+// These must implement the Error trait which requires
+// implementing the Debug and Display traits.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GetDogsError {
+    /// The file could not be read for a reason
+    /// that doesn't have its own variant.
+    BadFile(std::io::Error),
+    /// The file doesn't exist.
+    NotFound(std::io::Error),
+    /// The file exists but this user isn't allowed to read it.
+    PermissionDenied(std::io::Error),
+    /// The file isn't valid text in the encoding it appears to use.
+    /// The offset is that of the first bad byte from the start of the file.
+    Encoding {
+        offset: usize,
+        encoding: &'static str,
+    },
+    /// The file is empty or only holds whitespace.
+    Empty,
+    /// An async operation took longer than the limit given to `timeout`.
+    TimedOut(std::time::Duration),
+    /// An async operation was stopped by a `CancelToken`.
+    Cancelled,
+    /// Some dogs have the same key as an earlier dog.
+    /// This holds every duplicate found, not just the first.
+    Duplicates {
+        key: UniqueKey,
+        duplicates: Vec<Duplicate>,
+    },
+    /// Some dogs have breeds that aren't in the [`BreedRegistry`].
+    /// This holds every unknown breed found, not just the first.
+    UnknownBreeds(Vec<UnknownBreed>),
+    /// A line of a breed registry file can't be used.
+    /// Lines are numbered from 1.
+    BadRegistry { line: usize, reason: String },
+    /// A [`Query`] couldn't be parsed.
+    BadQuery(QueryError),
+    /// The file is not valid JSON or doesn't describe dogs.
+    BadJson(serde_json::error::Error),
+    /// The file is not valid YAML or doesn't describe dogs.
+    BadYaml(serde_yaml::Error),
+    /// The file is not valid TOML or doesn't describe dogs.
+    BadToml(toml::de::Error),
+    /// The file is not valid CSV or doesn't describe dogs.
+    BadCsv(csv::Error),
+    /// The file is not valid MessagePack or doesn't describe dogs.
+    #[cfg(feature = "msgpack")]
+    BadMessagePack(rmp_serde::decode::Error),
+    /// The file is not valid CBOR or doesn't describe dogs.
+    #[cfg(feature = "cbor")]
+    BadCbor(ciborium::de::Error<std::io::Error>),
+    /// A field of a dog in a JSON file has a value that can't be used,
+    /// such as a date that doesn't exist or a negative weight.
+    /// The index is the position of the dog in the array.
+    BadField {
+        index: usize,
+        field: String,
+        source: serde_json::error::Error,
+    },
+    /// The JSON was well-formed, but some dogs break the validation rules.
+    /// This holds every violation found, not just the first.
+    Invalid(Vec<Violation>),
+    /// The dogs could not be converted to JSON when saving.
+    CannotSerialize(serde_json::error::Error),
+    /// The dogs could not be converted to another format when saving.
+    CannotEncode {
+        format: FileFormat,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The temporary file could not be written when saving.
+    CannotWrite(std::io::Error),
+    /// The temporary file could not be renamed over the target when saving.
+    /// This is also the error when the directory can't be flushed after the rename,
+    /// in which case the target holds the new dogs but might not after a crash.
+    CannotRename(std::io::Error),
+    /// The file says it has a version of the format that isn't supported,
+    /// such as one written by a newer release.
+    UnsupportedVersion(String),
+    /// An older file couldn't be upgraded to the current version.
+    MigrationFailed { from: u32, to: u32, reason: String },
+    /// A [`DogStore`] couldn't carry out an operation.
+    /// The backend names the kind of store, such as "json" or "sqlite".
+    Storage {
+        backend: &'static str,
+        problem: StoreProblem,
+    },
+    /// Another error with descriptions of what was being done when it occurred.
+    /// These are added by the methods of the [`Context`] trait.
+    /// The frames are ordered from innermost to outermost.
+    WithContext {
+        frames: Vec<String>,
+        source: Box<GetDogsError>,
+    },
+    /// An operation retried by a [`RetryPolicy`] failed on its last attempt.
+    /// The earlier errors are those of the attempts before it, in order,
+    /// so the operation was attempted `earlier.len() + 1` times.
+    Retried {
+        earlier: Vec<GetDogsError>,
+        source: Box<GetDogsError>,
+    },
+    /// A dog read by [`stream_dogs`] couldn't be parsed.
+    /// The index is the position of the dog in the array or among the lines
+    /// of NDJSON, or None when the problem isn't inside a dog,
+    /// such as a trailing comma or text after the closing bracket.
+    /// The line and column, counted from 1, are where the problem is in the whole input.
+    /// Positions in the source error count from the start of the dog instead.
+    InStream {
+        index: Option<usize>,
+        line: usize,
+        column: usize,
+        source: Box<GetDogsError>,
+    },
+    /// The JSON around the dogs read by [`stream_dogs`] is broken,
+    /// such as a trailing comma after the last dog.
+    /// It is wrapped in [`InStream`](GetDogsError::InStream), which gives its position.
+    BadSyntax(SyntaxProblem),
+}
+
+// Make the variants of this enum directly available.
+use GetDogsError::*;
+
+// All of the Error trait methods have default implementations, so
+// no body is required here, but we will implement the source method.
+impl Error for GetDogsError {
+    // Returns the wrapped error, if any.
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            // The wrapped error type is implicitly cast to the trait object
+            // type &Error because it implements the Error trait.
+            BadFile(ref e) => Some(e),
+            NotFound(ref e) => Some(e),
+            PermissionDenied(ref e) => Some(e),
+            BadJson(ref e) => Some(e),
+            BadYaml(ref e) => Some(e),
+            BadToml(ref e) => Some(e),
+            BadCsv(ref e) => Some(e),
+            #[cfg(feature = "msgpack")]
+            BadMessagePack(ref e) => Some(e),
+            #[cfg(feature = "cbor")]
+            BadCbor(ref e) => Some(e),
+            CannotEncode { ref source, .. } => Some(source.as_ref()),
+            BadField { ref source, .. } => Some(source),
+            BadQuery(ref e) => Some(e),
+            // Validation, version and content failures don't wrap another error.
+            Invalid(_)
+            | UnsupportedVersion(_)
+            | MigrationFailed { .. }
+            | Encoding { .. }
+            | Empty
+            | TimedOut(_)
+            | Cancelled
+            | Duplicates { .. }
+            | UnknownBreeds(_)
+            | BadSyntax(_)
+            | BadRegistry { .. } => None,
+            CannotSerialize(ref e) => Some(e),
+            CannotWrite(ref e) => Some(e),
+            CannotRename(ref e) => Some(e),
+            Storage { ref problem, .. } => match *problem {
+                StoreProblem::Backend(ref e) => Some(e.as_ref()),
+                _ => None,
+            },
+            // This keeps the whole chain of sources reachable.
+            WithContext { ref source, .. }
+            | Retried { ref source, .. }
+            | InStream { ref source, .. } => Some(source.as_ref()),
+        }
+    }
+}
+
+impl std::fmt::Display for GetDogsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BadFile(ref e) => write!(f, "bad file: {}", e),
+            NotFound(ref e) => write!(f, "file not found: {}", e),
+            PermissionDenied(ref e) => write!(f, "permission denied: {}", e),
+            Encoding { offset, encoding } => {
+                write!(f, "the file is not valid {} at byte {}", encoding, offset)
+            }
+            Empty => write!(f, "the file is empty"),
+            TimedOut(limit) => write!(f, "timed out after {:?}", limit),
+            Cancelled => write!(f, "cancelled"),
+            Duplicates {
+                key,
+                ref duplicates,
+            } => {
+                write!(f, "duplicate dogs by {}:", key)?;
+                for d in duplicates {
+                    write!(f, "\n  {}", d)?;
+                }
+                Ok(())
+            }
+            UnknownBreeds(ref unknown) => {
+                write!(f, "unknown breeds:")?;
+                for u in unknown {
+                    write!(f, "\n  {}", u)?;
+                }
+                Ok(())
+            }
+            BadRegistry { line, ref reason } => {
+                write!(f, "bad breed registry: line {}: {}", line, reason)
+            }
+            BadQuery(ref e) => write!(f, "bad query: {}", e),
+            BadJson(ref e) => write!(f, "bad JSON: {}", e),
+            BadSyntax(problem) => write!(f, "bad JSON: {}", problem),
+            BadYaml(ref e) => write!(f, "bad YAML: {}", e),
+            BadToml(ref e) => write!(f, "bad TOML: {}", e),
+            BadCsv(ref e) => write!(f, "bad CSV: {}", e),
+            #[cfg(feature = "msgpack")]
+            BadMessagePack(ref e) => write!(f, "bad MessagePack: {}", e),
+            #[cfg(feature = "cbor")]
+            BadCbor(ref e) => write!(f, "bad CBOR: {}", e),
+            BadField {
+                index,
+                ref field,
+                ref source,
+            } => write!(f, "bad field: dog {} {}: {}", index, field, source),
+            Invalid(ref violations) => {
+                write!(f, "invalid dogs:")?;
+                for v in violations {
+                    write!(f, "\n  {}", v)?;
+                }
+                Ok(())
+            }
+            CannotSerialize(ref e) => write!(f, "cannot serialize dogs: {}", e),
+            CannotEncode { format, ref source } => {
+                write!(f, "cannot write dogs as {}: {}", format, source)
+            }
+            CannotWrite(ref e) => write!(f, "cannot write temporary file: {}", e),
+            CannotRename(ref e) => write!(f, "cannot replace file: {}", e),
+            UnsupportedVersion(ref version) => {
+                write!(f, "unsupported file version: {}", version)
+            }
+            MigrationFailed {
+                from,
+                to,
+                ref reason,
+            } => write!(
+                f,
+                "cannot upgrade file from version {} to {}: {}",
+                from, to, reason
+            ),
+            Storage {
+                backend,
+                ref problem,
+            } => write!(f, "{} storage: {}", backend, problem),
+            WithContext {
+                ref frames,
+                ref source,
+            } => context::fmt_frames(f, frames, source),
+            Retried {
+                ref earlier,
+                ref source,
+            } => retry::fmt_attempts(f, earlier, source),
+            InStream {
+                index: Some(index),
+                line,
+                column,
+                ref source,
+            } => write!(
+                f,
+                "dog {} at line {} column {}: {}",
+                index, line, column, source
+            ),
+            InStream {
+                index: None,
+                line,
+                column,
+                ref source,
+            } => write!(f, "line {} column {}: {}", line, column, source),
+        }
+    }
+}
+
+impl GetDogsError {
+    /// The process exit code used by the command-line tool for this error.
+    ///
+    /// Each variant has its own code so shell scripts
+    /// can react to bad files differently from bad JSON.
+    /// These codes will not change once assigned.
+    ///
+    /// | Code | Variant              |
+    /// |------|----------------------|
+    /// | 3    | `BadFile`            |
+    /// | 4    | `BadJson`            |
+    /// | 5    | `Invalid`            |
+    /// | 6    | `CannotSerialize`    |
+    /// | 7    | `CannotWrite`        |
+    /// | 8    | `CannotRename`       |
+    /// | 9    | `BadYaml`            |
+    /// | 10   | `BadToml`            |
+    /// | 11   | `BadCsv`             |
+    /// | 12   | `BadField`           |
+    /// | 13   | `UnsupportedVersion` |
+    /// | 14   | `MigrationFailed`    |
+    /// | 15   | `Storage`            |
+    /// | 16   | `NotFound`           |
+    /// | 17   | `PermissionDenied`   |
+    /// | 18   | `Encoding`           |
+    /// | 19   | `Empty`              |
+    /// | 20   | `TimedOut`           |
+    /// | 21   | `Cancelled`          |
+    /// | 22   | `Duplicates`         |
+    /// | 23   | `UnknownBreeds`      |
+    /// | 24   | `BadRegistry`        |
+    /// | 25   | `BadQuery`           |
+    /// | 26   | `BadMessagePack`     |
+    /// | 27   | `CannotEncode`       |
+    /// | 28   | `BadCbor`            |
+    /// | 29   | `BadSyntax`          |
+    ///
+    /// Errors with context or a stream position use the code of the error they wrap,
+    /// and retried errors use the code of the last attempt's error.
+    /// The tool uses 1 for other failures and 2 for usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            BadFile(_) => 3,
+            BadJson(_) => 4,
+            Invalid(_) => 5,
+            CannotSerialize(_) => 6,
+            CannotWrite(_) => 7,
+            CannotRename(_) => 8,
+            BadYaml(_) => 9,
+            BadToml(_) => 10,
+            BadCsv(_) => 11,
+            BadField { .. } => 12,
+            UnsupportedVersion(_) => 13,
+            MigrationFailed { .. } => 14,
+            Storage { .. } => 15,
+            NotFound(_) => 16,
+            PermissionDenied(_) => 17,
+            Encoding { .. } => 18,
+            Empty => 19,
+            TimedOut(_) => 20,
+            Cancelled => 21,
+            Duplicates { .. } => 22,
+            UnknownBreeds(_) => 23,
+            BadRegistry { .. } => 24,
+            BadQuery(_) => 25,
+            #[cfg(feature = "msgpack")]
+            BadMessagePack(_) => 26,
+            CannotEncode { .. } => 27,
+            #[cfg(feature = "cbor")]
+            BadCbor(_) => 28,
+            BadSyntax(_) => 29,
+            WithContext { ref source, .. }
+            | Retried { ref source, .. }
+            | InStream { ref source, .. } => source.exit_code(),
+        }
+    }
+}
+
+// The "From" trait converts values of one type to another.
+// Having the following implementations enables
+// using the ? operator in the get_dogs3 function below.
+// I/O errors that have their own variant are separated from the rest.
+impl From<std::io::Error> for GetDogsError {
+    fn from(other: std::io::Error) -> Self {
+        match other.kind() {
+            std::io::ErrorKind::NotFound => NotFound(other),
+            std::io::ErrorKind::PermissionDenied => PermissionDenied(other),
+            _ => BadFile(other),
+        }
+    }
+}
+impl From<QueryError> for GetDogsError {
+    fn from(other: QueryError) -> Self {
+        BadQuery(other)
+    }
+}
+impl From<serde_json::error::Error> for GetDogsError {
+    fn from(other: serde_json::error::Error) -> Self {
+        BadJson(other)
+    }
+}
+
+// Let's look at three versions of a function that
+// reads a JSON file describing dogs and parses it
+// to create a vector of Dog instances.
+
+/// Reads dogs from a JSON file, returning a boxed error on failure.
+///
+/// With this version callers cannot easily distinguish between
+/// the two types of errors that can occur,
+/// std::io:Error from failing to read the file and
+/// serde_json::error::Error from failing to parse the JSON.
+/// This approach is fine when callers only need to
+/// know if an error occurred and print an error message.
+/// Like the other loaders, it also reads the versioned files
+/// written by [`save_dogs`], and a problem with their version
+/// is a third kind of error, a [`GetDogsError`].
+/// Handling different kinds of errors differently is messy:
+///
+/// ```no_run
+/// # use rust_error_handling::get_dogs1;
+/// match get_dogs1("./dogs.json") {
+///     Ok(dogs) => println!("{:?}", dogs),
+///     Err(e) => {
+///         if let Some(e) = e.downcast_ref::<std::io::Error>() {
+///             eprintln!("bad file: {:?}", e);
+///         } else if let Some(e) = e.downcast_ref::<serde_json::error::Error>() {
+///             eprintln!("bad json {:?}", e);
+///         } else {
+///             eprintln!("some other kind of error");
+///         }
+///     }
+/// }
+/// ```
+pub fn get_dogs1(file_path: &str) -> Result<Vec<Dog>, Box<dyn Error>> {
+    get_dogs1_from(&RealFileSystem, file_path)
+}
+
+/// Like [`get_dogs1`], but reads the file from a [`FileSystem`].
+pub fn get_dogs1_from(fs: &dyn FileSystem, file_path: &str) -> Result<Vec<Dog>, Box<dyn Error>> {
+    let result = telemetry::traced(telemetry::load(file_path, "json"), || {
+        let json = fs.read_to_string(file_path).map_err(BadFile)?;
+        telemetry::record_bytes(json.len());
+        let dogs = json::dogs_from_str(&json)?;
+        telemetry::record_dogs(dogs.len());
+        Ok(dogs)
+    });
+    // Read and parse failures are returned as the io and serde_json errors
+    // so they can be downcast as shown above.
+    match result {
+        Ok(dogs) => Ok(dogs),
+        Err(BadFile(e)) => Err(Box::new(e)),
+        Err(BadJson(e)) | Err(BadField { source: e, .. }) => Err(Box::new(e)),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+/// The result type returned by functions that can fail with a [`GetDogsError`].
+// If we have many functions with this same return type,
+// Result<some-type, GetDogsError> {
+// we can reduce the repetition by defining a type alias.
+pub type MyResult<T> = std::result::Result<T, GetDogsError>;
+
+/// Reads dogs from a JSON file, matching explicitly on each failure.
+///
+/// With this version callers can distinguish between the
+/// two types of errors by matching on the GetDogsError variants.
+/// Every failure to read the file is reported as BadFile,
+/// and every failure to parse it as BadJson, except that
+/// versioned files can also fail with their version's variants.
+pub fn get_dogs2(file_path: &str) -> MyResult<Vec<Dog>> {
+    get_dogs2_from(&RealFileSystem, file_path)
+}
+
+/// Like [`get_dogs2`], but reads the file from a [`FileSystem`].
+pub fn get_dogs2_from(fs: &dyn FileSystem, file_path: &str) -> MyResult<Vec<Dog>> {
+    telemetry::traced(telemetry::load(file_path, "json"), || {
+        match fs.read_to_string(file_path) {
+            Ok(json) => match json::dogs_from_str(&json) {
+                Ok(dogs) => {
+                    telemetry::record_bytes(json.len());
+                    telemetry::record_dogs(dogs.len());
+                    Ok(dogs)
+                }
+                // Only get_dogs3 reports bad field values separately.
+                Err(BadField { source, .. }) => Err(BadJson(source)),
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(BadFile(e)),
+        }
+    })
+}
+
+/// Reads dogs from a JSON file and validates them.
+///
+/// This version takes advantage of the fact that
+/// GetDogsError implements the From trait for
+/// each of the kinds of errors that can occur.
+/// This enables using the ? operator because errors of those
+/// types will automatically be converted to the GetDogsError type.
+/// Unlike the other versions, it reports bad field values as BadField,
+/// reads UTF-16 files, and reports missing, unreadable, badly encoded
+/// and empty files with their own variants rather than BadFile.
+pub fn get_dogs3(file_path: &str) -> MyResult<Vec<Dog>> {
+    get_dogs3_from(&RealFileSystem, file_path)
+}
+
+/// Like [`get_dogs3`], but reads the file from a [`FileSystem`].
+// Like the other loaders, this runs in a tracing span.
+// See the telemetry module for what is recorded.
+pub fn get_dogs3_from(fs: &dyn FileSystem, file_path: &str) -> MyResult<Vec<Dog>> {
+    telemetry::traced(telemetry::load(file_path, "json"), || {
+        let json = read_text(fs, file_path)?;
+        let dogs = json::dogs_from_str(&json)?;
+        telemetry::record_dogs(dogs.len());
+        validate_dogs(&dogs)?;
+        Ok(dogs)
+    })
+}
@@ -1,146 +1,128 @@
-use serde::{Deserialize, Serialize};
-use std::error::Error;
-use std::fmt;
-use std::fs::read_to_string;
+use rust_error_handling::{
+    read_text, render_json_error, ErrorReport, GetDogsError, RealFileSystem,
+};
+use std::io::IsTerminal;
+use std::process;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
 
-// This is a custom error type.
-// It enables callers that receive this kind of error
-// to handle different error causes differently.
-// These must implement the Error trait which requires
-// implementing the Debug and Display traits.
-#[derive(Debug)]
-pub enum GetDogsError {
-    BadFile(std::io::Error),
-    BadJson(serde_json::error::Error),
-}
+mod cli;
+mod commands;
+
+use cli::{Args, LogFormat};
+use commands::Failure;
 
 // Make the variants of this enum directly available.
 use GetDogsError::*;
 
-// All of the Error trait methods have default implementations, so
-// no body is required here, but we will implement the source method.
-impl Error for GetDogsError {
-    // Returns the wrapped error, if any.
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match *self {
-            // The wrapped error type is implicitly cast to the trait object
-            // type &Error because it implements the Error trait.
-            BadFile(ref e) => Some(e),
-            BadJson(ref e) => Some(e),
+// Each kind of failure exits with its own code
+// so shell scripts can react to them differently.
+// See cli::USAGE for the list of codes.
+fn main() {
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, cli::USAGE);
+            process::exit(2);
         }
-    }
-}
+    };
+
+    init_logging(&args);
+
+    let result = commands::run(&args);
+    #[cfg(feature = "metrics")]
+    write_metrics(&args);
 
-impl std::fmt::Display for GetDogsError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            BadFile(ref e) => write!(f, "bad file: {}", e),
-            BadJson(ref e) => write!(f, "bad JSON: {}", e),
+    if let Err(failure) = result {
+        let code = failure.exit_code();
+        match failure {
+            Failure::Dogs(e, path) => {
+                let path = path.as_deref().unwrap_or(&args.file);
+                if args.json_errors {
+                    eprintln!("{}", ErrorReport::new(&e, Some(path)).to_json());
+                } else {
+                    report_error(path, e);
+                }
+            }
+            Failure::Other(message) => {
+                if args.json_errors {
+                    eprintln!("{}", other_report(message, &args.file).to_json());
+                } else {
+                    eprintln!("error: {}", message);
+                }
+            }
+            Failure::Reported(_) => {}
         }
+        process::exit(code);
     }
 }
 
-// The "From" trait converts values of one type to another.
-// Having the following implementations enables
-// using the ? operator in the get_dogs3 function below.
-impl From<std::io::Error> for GetDogsError {
-    fn from(other: std::io::Error) -> Self {
-        BadFile(other)
-    }
-}
-impl From<serde_json::error::Error> for GetDogsError {
-    fn from(other: serde_json::error::Error) -> Self {
-        BadJson(other)
+// Logs go to stderr so they don't mix with the dogs printed on stdout.
+// Each load and save is logged when its span closes,
+// which is when its fields have all been recorded.
+fn init_logging(args: &Args) {
+    let filter = match args.log_level {
+        Some(level) => EnvFilter::default().add_directive(level.into()),
+        // Without this default, EnvFilter would log errors when RUST_LOG isn't set.
+        None => EnvFilter::builder()
+            .with_default_directive(LevelFilter::OFF.into())
+            .from_env_lossy(),
+    };
+    let logs = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_writer(std::io::stderr);
+    match args.log_format {
+        LogFormat::Text => logs.init(),
+        LogFormat::Json => logs.json().init(),
     }
 }
 
-// This struct can be deserialized from JSON and serialized to JSON.
-#[derive(Deserialize, Serialize, Debug)]
-struct Dog {
-    name: String,
-    breed: String,
-}
-
-// Let's look at three versions of a function that
-// reads a JSON file describing dogs and parses it
-// to create a vector of Dog instances.
-
-// With this version callers cannot easily distinguish between
-// the two types of errors that can occur,
-// std::io:Error from failing to read the file and
-// serde_json::error::Error from failing to parse the JSON.
-// This approach is fine when callers only need to
-// know if an error occurred and print an error message.
-fn get_dogs1(file_path: &str) -> Result<Vec<Dog>, Box<dyn Error>> {
-    let json = read_to_string(file_path)?;
-    let dogs: Vec<Dog> = serde_json::from_str(&json)?;
-    Ok(dogs)
-}
-
-// If we have many functions with this same return type,
-// Result<some-type, GetDogsError> {
-// we can reduce the repetition by defining a type alias.
-pub type MyResult<T> = std::result::Result<T, GetDogsError>;
-
-// With this version callers can distinguish between the
-// two types of errors by matching on the GetDogsError variants.
-fn get_dogs2(file_path: &str) -> MyResult<Vec<Dog>> {
-    match read_to_string(file_path) {
-        Ok(json) => match serde_json::from_str(&json) {
-            Ok(dogs) => Ok(dogs),
-            Err(e) => Err(BadJson(e)),
-        },
-        Err(e) => Err(BadFile(e)),
+// The file is replaced rather than appended to, so it can be read
+// by the textfile collector of the Prometheus node exporter.
+// Failing to write it doesn't change the exit code of the command.
+#[cfg(feature = "metrics")]
+fn write_metrics(args: &Args) {
+    if let Some(path) = &args.metrics {
+        let text = rust_error_handling::Metrics::global().to_prometheus();
+        if let Err(e) = std::fs::write(path, text) {
+            eprintln!("warning: cannot write metrics to {}: {}", path, e);
+        }
     }
 }
 
-// This version takes advantage of the fact that
-// GetDogsError implements the From trait for
-// each of the kinds of errors that can occur.
-// This enables using the ? operator because errors of those
-// types will automatically be converted to the GetDogsError type.
-fn get_dogs3(file_path: &str) -> MyResult<Vec<Dog>> {
-    let json = read_to_string(file_path)?;
-    let dogs: Vec<Dog> = serde_json::from_str(&json)?;
-    Ok(dogs)
-}
-
-// If the main function has this return type, it can use the ? operator.
-//fn main() -> Result<(), Box<dyn Error>> {
-fn main() {
-    let file_path = "./dogs.json";
-
-    /*
-    // With the first approach we can easily detect that an error has occurred.
-    if let Ok(dogs) = get_dogs1(file_path) {
-        dbg!(dogs);
-    } else {
-        eprintln!("failed to get dogs, but don't know why");
+// This describes failures that don't come from the library
+// in the same shape as GetDogsError reports.
+fn other_report(message: String, path: &str) -> ErrorReport {
+    ErrorReport {
+        kind: "Other",
+        code: "E001",
+        message,
+        path: Some(path.to_string()),
+        line: None,
+        column: None,
+        context: Vec::new(),
+        sources: Vec::new(),
+        violations: Vec::new(),
     }
-    */
+}
 
-    /*
-    // But handling different kinds of errors differently is messy.
-    match get_dogs1(file_path) {
-        Ok(dogs) => println!("{:?}", dogs),
-        Err(e) => {
-            if let Some(e) = e.downcast_ref::<std::io::Error>() {
-                eprintln!("bad file: {:?}", e);
-            } else if let Some(e) = e.downcast_ref::<serde_json::error::Error>() {
-                eprintln!("bad json {:?}", e);
-            } else {
-                eprintln!("some other kind of error");
+// The alternate format shows each context frame on its own line.
+fn report_error(file_path: &str, err: GetDogsError) {
+    if let BadJson(e) = err.root() {
+        // The file was readable, so read it again to show
+        // the lines around the position where parsing failed.
+        if let (true, Ok(source)) = (e.line() > 0, read_text(&RealFileSystem, file_path)) {
+            eprint!("{}", render_json_error(file_path, &source, e));
+            if let WithContext { frames, .. } = &err {
+                for frame in frames.iter().rev() {
+                    eprintln!("note: while {}", frame);
+                }
             }
+            return;
         }
     }
-    */
-
-    // With the second and third approaches it is much easier
-    // to handle different kinds of errors differently.
-    //match get_dogs2(file_path) {
-    match get_dogs3(file_path) {
-        Ok(dogs) => println!("{:?}", dogs),
-        Err(BadFile(e)) => eprintln!("bad file: {}", e),
-        Err(BadJson(e)) => eprintln!("bad json: {}", e),
-    }
+    eprintln!("error: {:#}", err);
 }
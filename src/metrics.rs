@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::MyResult;
+
+// This is shared by every load and save in the process.
+static GLOBAL: Metrics = Metrics::new();
+
+/// Counters of the loads and saves that have run,
+/// with failures counted separately for each [`GetDogsError`](crate::GetDogsError) variant.
+///
+/// Every loader and saver records itself in [`Metrics::global`].
+/// Other instances are only changed by calling [`record`](Metrics::record).
+#[derive(Debug)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+// Operations are "load" or "save", and kinds come from GetDogsError::kind.
+#[derive(Debug)]
+struct Counters {
+    total: BTreeMap<&'static str, u64>,
+    successes: BTreeMap<&'static str, u64>,
+    failures: BTreeMap<(&'static str, &'static str), u64>,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            counters: Mutex::new(Counters {
+                total: BTreeMap::new(),
+                successes: BTreeMap::new(),
+                failures: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// The metrics of every load and save in this process.
+    pub fn global() -> &'static Metrics {
+        &GLOBAL
+    }
+
+    /// Counts an operation, such as "load" or "save", and its result.
+    pub fn record<T>(&self, operation: &'static str, result: &MyResult<T>) {
+        let mut counters = self.counters();
+        *counters.total.entry(operation).or_default() += 1;
+        match result {
+            Ok(_) => *counters.successes.entry(operation).or_default() += 1,
+            Err(e) => *counters.failures.entry((operation, e.kind())).or_default() += 1,
+        }
+    }
+
+    /// The number of times an operation has run.
+    pub fn total(&self, operation: &str) -> u64 {
+        self.counters().total.get(operation).copied().unwrap_or(0)
+    }
+
+    /// The number of times an operation has succeeded.
+    pub fn successes(&self, operation: &str) -> u64 {
+        self.counters()
+            .successes
+            .get(operation)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The number of times an operation has failed with a kind of error,
+    /// such as "NotFound".
+    pub fn failures(&self, operation: &str, kind: &str) -> u64 {
+        let counters = self.counters();
+        counters
+            .failures
+            .get(&(operation, kind))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Writes the counters in the Prometheus text exposition format.
+    /// Operations and kinds that haven't happened yet are left out,
+    /// as Prometheus expects for labeled counters.
+    pub fn to_prometheus(&self) -> String {
+        let counters = self.counters();
+        let mut text = String::new();
+        // Writing to a String cannot fail.
+        let mut counter = |name: &str, help: &str, values: Vec<(String, u64)>| {
+            let _ = writeln!(text, "# HELP {} {}", name, help);
+            let _ = writeln!(text, "# TYPE {} counter", name);
+            for (labels, value) in values {
+                let _ = writeln!(text, "{}{{{}}} {}", name, labels, value);
+            }
+        };
+        let by_operation = |map: &BTreeMap<&str, u64>| {
+            map.iter()
+                .map(|(operation, count)| (format!("operation=\"{}\"", operation), *count))
+                .collect()
+        };
+        counter(
+            "dogs_operations_total",
+            "Loads and saves of dog files.",
+            by_operation(&counters.total),
+        );
+        counter(
+            "dogs_operation_successes_total",
+            "Loads and saves of dog files that succeeded.",
+            by_operation(&counters.successes),
+        );
+        counter(
+            "dogs_operation_failures_total",
+            "Loads and saves of dog files that failed, by kind of error.",
+            counters
+                .failures
+                .iter()
+                .map(|((operation, kind), count)| {
+                    let labels = format!("operation=\"{}\",kind=\"{}\"", operation, kind);
+                    (labels, *count)
+                })
+                .collect(),
+        );
+        text
+    }
+
+    // A panic while the lock was held can't have left the counts
+    // inconsistent enough to matter, so a poisoned lock is still used.
+    fn counters(&self) -> std::sync::MutexGuard<'_, Counters> {
+        self.counters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
@@ -0,0 +1,156 @@
+use serde::Serialize;
+
+use crate::GetDogsError::{self, *};
+use crate::{report, ErrorReport, StoreProblem, ViolationReport};
+
+impl GetDogsError {
+    /// The HTTP status code used by the server for this error.
+    ///
+    /// | Status | Variants                                          |
+    /// |--------|---------------------------------------------------|
+    /// | 400    | `BadQuery`                                        |
+    /// | 404    | `Storage` when no dog has the name                |
+    /// | 409    | `Storage` when a dog already has the name,        |
+    /// |        | and `Duplicates`                                  |
+    /// | 422    | `Invalid` and `UnknownBreeds`                     |
+    /// | 500    | the parse, content, version and save errors,      |
+    /// |        | `BadRegistry`,                                    |
+    /// |        | and `Storage` when the backend fails              |
+    /// | 503    | `BadFile`, `NotFound` and `PermissionDenied`      |
+    /// | 503    | `TimedOut` and `Cancelled`                        |
+    ///
+    /// A file that can't be read is 503 because the server
+    /// may work again once the file is restored.
+    /// A file that can be read but not parsed is 500
+    /// because the server's own data is broken.
+    /// Errors with context use the status of the error they wrap.
+    pub fn http_status(&self) -> u16 {
+        match *self {
+            BadFile(_) | NotFound(_) | PermissionDenied(_) => 503,
+            TimedOut(_) | Cancelled => 503,
+            Invalid(_) | UnknownBreeds(_) => 422,
+            BadQuery(_) => 400,
+            Duplicates { .. } => 409,
+            Storage { ref problem, .. } => match *problem {
+                StoreProblem::Missing(_) => 404,
+                StoreProblem::Duplicate(_) => 409,
+                StoreProblem::Backend(_) => 500,
+            },
+            Encoding { .. }
+            | Empty
+            | BadJson(_)
+            | BadSyntax(_)
+            | BadYaml(_)
+            | BadToml(_)
+            | BadCsv(_)
+            | BadField { .. }
+            | UnsupportedVersion(_)
+            | MigrationFailed { .. }
+            | CannotSerialize(_)
+            | CannotWrite(_)
+            | CannotRename(_)
+            | CannotEncode { .. }
+            | BadRegistry { .. } => 500,
+            #[cfg(feature = "msgpack")]
+            BadMessagePack(_) => 500,
+            #[cfg(feature = "cbor")]
+            BadCbor(_) => 500,
+            WithContext { ref source, .. }
+            | Retried { ref source, .. }
+            | InStream { ref source, .. } => source.http_status(),
+        }
+    }
+}
+
+/// An error response body in the problem details format of RFC 7807,
+/// sent with the content type `application/problem+json`.
+///
+/// The `code` and `violations` members are extensions
+/// that match those of an [`ErrorReport`].
+#[derive(Debug, Serialize)]
+pub struct ProblemDetails {
+    /// A URI identifying the kind of problem, made from the code.
+    #[serde(rename = "type")]
+    pub type_uri: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: &'static str,
+    pub violations: Vec<ViolationReport>,
+}
+
+impl ProblemDetails {
+    /// Describes an error with the status from [`GetDogsError::http_status`].
+    pub fn new(err: &GetDogsError) -> Self {
+        let report = ErrorReport::new(err, None);
+        ProblemDetails {
+            type_uri: type_uri(report.code),
+            title: title(err),
+            status: err.http_status(),
+            detail: report.message,
+            code: report.code,
+            violations: report.violations,
+        }
+    }
+
+    /// Describes a failure that doesn't come from the library,
+    /// such as a request for an unknown path.
+    /// These use the code E001 like the command-line tool's other failures.
+    pub fn other(status: u16, title: &str, detail: impl Into<String>) -> Self {
+        ProblemDetails {
+            type_uri: type_uri("E001"),
+            title: title.to_string(),
+            status,
+            detail: detail.into(),
+            code: "E001",
+            violations: Vec::new(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        report::to_json_line(self)
+    }
+}
+
+fn type_uri(code: &str) -> String {
+    format!("urn:rust-error-handling:error:{}", code)
+}
+
+// Titles are short and the same for every occurrence of a kind of problem,
+// as RFC 7807 recommends. The detail holds the specifics.
+fn title(err: &GetDogsError) -> String {
+    let title = match *err.root() {
+        NotFound(_) => "Dogs file is missing",
+        PermissionDenied(_) => "Dogs file is not readable by the server",
+        BadFile(_) => "Dogs file cannot be read",
+        Encoding { .. } => "Dogs file has a bad encoding",
+        Empty => "Dogs file is empty",
+        TimedOut(_) => "Dogs file took too long",
+        Cancelled => "Request was cancelled",
+        Duplicates { .. } => "Dogs are duplicated",
+        BadJson(_) | BadSyntax(_) | BadYaml(_) | BadToml(_) | BadCsv(_) | BadField { .. } => {
+            "Dogs file is malformed"
+        }
+        #[cfg(feature = "msgpack")]
+        BadMessagePack(_) => "Dogs file is malformed",
+        #[cfg(feature = "cbor")]
+        BadCbor(_) => "Dogs file is malformed",
+        UnsupportedVersion(_) | MigrationFailed { .. } => "Dogs file has an unusable version",
+        Invalid(_) => "Dogs break the validation rules",
+        UnknownBreeds(_) => "Dogs have unknown breeds",
+        BadRegistry { .. } => "Breed registry is malformed",
+        BadQuery(_) => "Query is malformed",
+        CannotSerialize(_) | CannotEncode { .. } | CannotWrite(_) | CannotRename(_) => {
+            "Dogs cannot be saved"
+        }
+        Storage { ref problem, .. } => match *problem {
+            StoreProblem::Missing(_) => "Dog not found",
+            StoreProblem::Duplicate(_) => "Dog already exists",
+            StoreProblem::Backend(_) => "Storage failed",
+        },
+        WithContext { ref source, .. }
+        | Retried { ref source, .. }
+        | InStream { ref source, .. } => return title(source),
+    };
+    title.to_string()
+}
@@ -0,0 +1,541 @@
+use regex::Regex;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Date, Dog, MyResult};
+
+/// A filter, sort order and limit for a list of dogs,
+/// parsed from text such as
+/// `breed = "Whippet" and name ~ "^C" sort by weight desc limit 3`.
+///
+/// Every part is optional, so an empty query keeps every dog.
+///
+/// - Conditions compare a field with a value using
+///   `=`, `!=`, `<`, `<=`, `>`, `>=`, or `~` for a regular expression match.
+///   They can be combined with `and`, `or`, `not` and parentheses.
+/// - The fields are name, breed, birth_date, sex, weight,
+///   color, microchip, owner and notes.
+/// - Values are quoted strings or bare words and numbers.
+///   Weights are compared as numbers and birth dates as dates.
+/// - A condition on a field that a dog doesn't have is false.
+/// - `sort by` takes fields separated by commas,
+///   each optionally followed by `asc` or `desc`.
+///   Dogs without a field sort after those with it.
+/// - `limit` keeps at most that many dogs after sorting.
+///
+/// Keywords are not case sensitive, but values are.
+#[derive(Clone, Debug)]
+pub struct Query {
+    filter: Option<Expr>,
+    sort: Vec<(Field, Direction)>,
+    limit: Option<usize>,
+}
+
+/// Why a query couldn't be parsed.
+/// The position is the number of characters before the problem.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl Error for QueryError {}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        let tokens = tokenize(text)?;
+        Parser {
+            tokens,
+            next: 0,
+            end: text.chars().count(),
+        }
+        .query()
+    }
+
+    /// Keeps the dogs that match the filter, in the query's order.
+    /// Dogs that compare equal keep their original order.
+    pub fn run(&self, mut dogs: Vec<Dog>) -> Vec<Dog> {
+        if let Some(filter) = &self.filter {
+            dogs.retain(|dog| filter.matches(dog));
+        }
+        if !self.sort.is_empty() {
+            dogs.sort_by(|a, b| {
+                self.sort
+                    .iter()
+                    .map(|&(field, direction)| compare(field, direction, a, b))
+                    .find(|&ordering| ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        if let Some(limit) = self.limit {
+            dogs.truncate(limit);
+        }
+        dogs
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::parse(s)
+    }
+}
+
+/// Parses a query and runs it on the dogs.
+///
+/// A query that can't be parsed fails with [`GetDogsError::BadQuery`](crate::GetDogsError::BadQuery).
+pub fn query_dogs(dogs: Vec<Dog>, query: &str) -> MyResult<Vec<Dog>> {
+    Ok(Query::parse(query)?.run(dogs))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Name,
+    Breed,
+    BirthDate,
+    Sex,
+    Weight,
+    Color,
+    Microchip,
+    Owner,
+    Notes,
+}
+
+// The value of a field, which is compared with others of the same kind.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+enum Value {
+    Text(String),
+    Number(f64),
+    Date(Date),
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        let field = match name {
+            "name" => Field::Name,
+            "breed" => Field::Breed,
+            "birth_date" => Field::BirthDate,
+            "sex" => Field::Sex,
+            "weight" => Field::Weight,
+            "color" => Field::Color,
+            "microchip" => Field::Microchip,
+            "owner" => Field::Owner,
+            "notes" => Field::Notes,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn value(self, dog: &Dog) -> Option<Value> {
+        let text = |s: &str| Value::Text(s.to_string());
+        match self {
+            Field::Name => Some(text(dog.name())),
+            Field::Breed => Some(text(dog.breed())),
+            Field::BirthDate => dog.birth_date().map(Value::Date),
+            Field::Sex => dog
+                .sex()
+                .map(|sex| Value::Text(format!("{:?}", sex).to_lowercase())),
+            Field::Weight => dog.weight().map(Value::Number),
+            Field::Color => dog.color().map(text),
+            Field::Microchip => dog.microchip().map(text),
+            Field::Owner => dog.owner().map(text),
+            Field::Notes => dog.notes().map(text),
+        }
+    }
+
+    // Converts a value in a query to the kind this field holds.
+    fn parse_value(self, text: &str) -> Result<Value, String> {
+        match self {
+            Field::Weight => text
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("weight must be compared with a number, not {:?}", text)),
+            Field::BirthDate => text.parse().map(Value::Date),
+            _ => Ok(Value::Text(text.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    Ascending,
+    Descending,
+}
+
+// Dogs without the field sort last in both directions.
+fn compare(field: Field, direction: Direction, a: &Dog, b: &Dog) -> Ordering {
+    match (field.value(a), field.value(b)) {
+        (Some(a), Some(b)) => {
+            let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+            match direction {
+                Direction::Ascending => ordering,
+                Direction::Descending => ordering.reverse(),
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Compare(Field, Operator, Value),
+    Matches(Field, Regex),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn matches(&self, dog: &Dog) -> bool {
+        match self {
+            Expr::Compare(field, operator, value) => match field.value(dog) {
+                Some(actual) => {
+                    let ordering = actual.partial_cmp(value);
+                    match operator {
+                        Operator::Equal => ordering == Some(Ordering::Equal),
+                        Operator::NotEqual => ordering != Some(Ordering::Equal),
+                        Operator::Less => ordering == Some(Ordering::Less),
+                        Operator::LessOrEqual => ordering.is_some_and(Ordering::is_le),
+                        Operator::Greater => ordering == Some(Ordering::Greater),
+                        Operator::GreaterOrEqual => ordering.is_some_and(Ordering::is_ge),
+                    }
+                }
+                None => false,
+            },
+            Expr::Matches(field, regex) => match field.value(dog) {
+                Some(Value::Text(text)) => regex.is_match(&text),
+                _ => false,
+            },
+            Expr::Not(expr) => !expr.matches(dog),
+            Expr::And(left, right) => left.matches(dog) && right.matches(dog),
+            Expr::Or(left, right) => left.matches(dog) || right.matches(dog),
+        }
+    }
+}
+
+// Words are bare words and numbers, such as field names and keywords.
+// Strings are quoted values, which are never keywords.
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Str(String),
+    Symbol(&'static str),
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Word(word) => write!(f, "{:?}", word),
+            TokenKind::Str(s) => write!(f, "string {:?}", s),
+            TokenKind::Symbol(symbol) => write!(f, "{:?}", symbol),
+        }
+    }
+}
+
+// Longer symbols come first so "<=" isn't read as "<" then "=".
+const SYMBOLS: [&str; 10] = ["!=", "<=", ">=", "=", "<", ">", "~", "(", ")", ","];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let position = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let kind = if c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => {
+                        return Err(QueryError {
+                            position,
+                            message: "unterminated string".to_string(),
+                        })
+                    }
+                    Some('"') => break,
+                    // A backslash includes the next character as is,
+                    // so \" is a quote and \\ is a backslash.
+                    Some('\\') if i + 1 < chars.len() => {
+                        value.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&c) => {
+                        value.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            TokenKind::Str(value)
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| {
+            s.chars()
+                .enumerate()
+                .all(|(k, sc)| chars.get(i + k) == Some(&sc))
+        }) {
+            i += symbol.len();
+            TokenKind::Symbol(symbol)
+        } else if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.' | '-'))
+            {
+                i += 1;
+            }
+            TokenKind::Word(chars[start..i].iter().collect())
+        } else {
+            return Err(QueryError {
+                position,
+                message: format!("unexpected character {:?}", c),
+            });
+        };
+        tokens.push(Token { kind, position });
+    }
+    Ok(tokens)
+}
+
+// This is a recursive descent parser with one function per rule:
+//
+// query      = [or] ["sort" "by" key {"," key}] ["limit" number]
+// or         = and {"or" and}
+// and        = not {"and" not}
+// not        = "not" not | "(" or ")" | condition
+// condition  = field operator value
+// key        = field ["asc" | "desc"]
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    // The position reported for errors at the end of the query.
+    end: usize,
+}
+
+impl Parser {
+    fn query(mut self) -> Result<Query, QueryError> {
+        let filter = if self.peek_keyword("sort") || self.peek_keyword("limit") || self.at_end() {
+            None
+        } else {
+            Some(self.or()?)
+        };
+
+        let mut sort = Vec::new();
+        if self.take_keyword("sort") {
+            self.expect_keyword("by")?;
+            loop {
+                let field = self.field()?;
+                let direction = if self.take_keyword("desc") {
+                    Direction::Descending
+                } else {
+                    self.take_keyword("asc");
+                    Direction::Ascending
+                };
+                sort.push((field, direction));
+                if !self.take_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        let mut limit = None;
+        if self.take_keyword("limit") {
+            let position = self.position();
+            limit = match self.advance() {
+                Some(TokenKind::Word(word)) => word.parse().ok(),
+                _ => None,
+            };
+            if limit.is_none() {
+                return Err(self.error_at(position, "expected a number after limit"));
+            }
+        }
+
+        match self.tokens.get(self.next) {
+            None => Ok(Query {
+                filter,
+                sort,
+                limit,
+            }),
+            Some(token) => {
+                Err(self.error_at(token.position, &format!("unexpected {}", token.kind)))
+            }
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.and()?;
+        while self.take_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.not()?;
+        while self.take_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, QueryError> {
+        if self.take_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        if self.take_symbol("(") {
+            let expr = self.or()?;
+            if !self.take_symbol(")") {
+                return Err(self.error("expected \")\""));
+            }
+            return Ok(expr);
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Expr, QueryError> {
+        let field = self.field()?;
+        let position = self.position();
+        let operator = match self.advance() {
+            Some(TokenKind::Symbol("=")) => Some(Operator::Equal),
+            Some(TokenKind::Symbol("!=")) => Some(Operator::NotEqual),
+            Some(TokenKind::Symbol("<")) => Some(Operator::Less),
+            Some(TokenKind::Symbol("<=")) => Some(Operator::LessOrEqual),
+            Some(TokenKind::Symbol(">")) => Some(Operator::Greater),
+            Some(TokenKind::Symbol(">=")) => Some(Operator::GreaterOrEqual),
+            Some(TokenKind::Symbol("~")) => None,
+            _ => return Err(self.error_at(position, "expected an operator")),
+        };
+
+        let position = self.position();
+        let text = match self.advance() {
+            Some(TokenKind::Word(text)) | Some(TokenKind::Str(text)) => text,
+            _ => return Err(self.error_at(position, "expected a value")),
+        };
+        match operator {
+            Some(operator) => {
+                let value = field
+                    .parse_value(&text)
+                    .map_err(|message| self.error_at(position, &message))?;
+                Ok(Expr::Compare(field, operator, value))
+            }
+            None => {
+                if matches!(field, Field::BirthDate | Field::Weight) {
+                    return Err(self.error_at(position, "only text fields can be matched with ~"));
+                }
+                let regex = Regex::new(&text).map_err(|e| {
+                    self.error_at(position, &format!("bad pattern: {}", pattern_problem(&e)))
+                })?;
+                Ok(Expr::Matches(field, regex))
+            }
+        }
+    }
+
+    fn field(&mut self) -> Result<Field, QueryError> {
+        let position = self.position();
+        match self.advance() {
+            Some(TokenKind::Word(word)) => Field::from_name(&word.to_lowercase())
+                .ok_or_else(|| self.error_at(position, &format!("unknown field {:?}", word))),
+            _ => Err(self.error_at(position, "expected a field name")),
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.next >= self.tokens.len()
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map_or(self.end, |token| token.position)
+    }
+
+    fn advance(&mut self) -> Option<TokenKind> {
+        let token = self.tokens.get(self.next)?.kind.clone();
+        self.next += 1;
+        Some(token)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        match self.tokens.get(self.next) {
+            Some(Token {
+                kind: TokenKind::Word(word),
+                ..
+            }) => word.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn take_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), QueryError> {
+        if self.take_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", keyword)))
+        }
+    }
+
+    fn take_symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(
+            self.tokens.get(self.next),
+            Some(Token { kind: TokenKind::Symbol(s), .. }) if *s == symbol
+        );
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn error(&self, message: &str) -> QueryError {
+        self.error_at(self.position(), message)
+    }
+
+    fn error_at(&self, position: usize, message: &str) -> QueryError {
+        QueryError {
+            position,
+            message: message.to_string(),
+        }
+    }
+}
+
+// The regex crate describes syntax errors over several lines
+// with a copy of the pattern. Only the last line says what's wrong.
+fn pattern_problem(err: &regex::Error) -> String {
+    let text = err.to_string();
+    let last = text.lines().last().unwrap_or("");
+    last.trim_start_matches("error: ").to_string()
+}
@@ -0,0 +1,229 @@
+use serde::Serialize;
+use std::error::Error;
+
+use crate::GetDogsError::{self, *};
+
+impl GetDogsError {
+    /// The name of the variant, or of the wrapped variant for
+    /// errors with context. This is "BadFile" for a BadFile error.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            BadFile(_) => "BadFile",
+            NotFound(_) => "NotFound",
+            PermissionDenied(_) => "PermissionDenied",
+            Encoding { .. } => "Encoding",
+            Empty => "Empty",
+            TimedOut(_) => "TimedOut",
+            Cancelled => "Cancelled",
+            Duplicates { .. } => "Duplicates",
+            UnknownBreeds(_) => "UnknownBreeds",
+            BadRegistry { .. } => "BadRegistry",
+            BadQuery(_) => "BadQuery",
+            #[cfg(feature = "msgpack")]
+            BadMessagePack(_) => "BadMessagePack",
+            #[cfg(feature = "cbor")]
+            BadCbor(_) => "BadCbor",
+            CannotEncode { .. } => "CannotEncode",
+            BadJson(_) => "BadJson",
+            BadSyntax(_) => "BadSyntax",
+            BadYaml(_) => "BadYaml",
+            BadToml(_) => "BadToml",
+            BadCsv(_) => "BadCsv",
+            BadField { .. } => "BadField",
+            UnsupportedVersion(_) => "UnsupportedVersion",
+            MigrationFailed { .. } => "MigrationFailed",
+            Storage { .. } => "Storage",
+            Invalid(_) => "Invalid",
+            CannotSerialize(_) => "CannotSerialize",
+            CannotWrite(_) => "CannotWrite",
+            CannotRename(_) => "CannotRename",
+            WithContext { ref source, .. }
+            | Retried { ref source, .. }
+            | InStream { ref source, .. } => source.kind(),
+        }
+    }
+
+    /// A stable identifier for the kind of error,
+    /// suitable for grouping failures on dashboards.
+    ///
+    /// Codes never change once assigned and are never reused,
+    /// even if a variant is removed.
+    /// The number matches the exit code used by the command-line tool,
+    /// which reports its other failures with the code E001.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            BadFile(_) => "E003",
+            BadJson(_) => "E004",
+            Invalid(_) => "E005",
+            CannotSerialize(_) => "E006",
+            CannotWrite(_) => "E007",
+            CannotRename(_) => "E008",
+            BadYaml(_) => "E009",
+            BadToml(_) => "E010",
+            BadCsv(_) => "E011",
+            BadField { .. } => "E012",
+            UnsupportedVersion(_) => "E013",
+            MigrationFailed { .. } => "E014",
+            Storage { .. } => "E015",
+            NotFound(_) => "E016",
+            PermissionDenied(_) => "E017",
+            Encoding { .. } => "E018",
+            Empty => "E019",
+            TimedOut(_) => "E020",
+            Cancelled => "E021",
+            Duplicates { .. } => "E022",
+            UnknownBreeds(_) => "E023",
+            BadRegistry { .. } => "E024",
+            BadQuery(_) => "E025",
+            #[cfg(feature = "msgpack")]
+            BadMessagePack(_) => "E026",
+            CannotEncode { .. } => "E027",
+            #[cfg(feature = "cbor")]
+            BadCbor(_) => "E028",
+            BadSyntax(_) => "E029",
+            WithContext { ref source, .. }
+            | Retried { ref source, .. }
+            | InStream { ref source, .. } => source.code(),
+        }
+    }
+}
+
+/// One validation failure in an [`ErrorReport`].
+#[derive(Debug, Serialize)]
+pub struct ViolationReport {
+    pub index: usize,
+    pub field: String,
+    pub message: String,
+}
+
+/// A description of an error that can be serialized,
+/// for programs that consume the tool's output.
+///
+/// Every field is always present so consumers can rely on the shape.
+/// Fields that don't apply to an error are null or empty.
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: &'static str,
+    pub code: &'static str,
+    pub message: String,
+    /// The file that was being loaded or saved, if known.
+    pub path: Option<String>,
+    /// The 1-based position of a parse error, where the parser provides it.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// The context frames, outermost first.
+    pub context: Vec<String>,
+    /// The messages of the chain of underlying errors, outermost first.
+    pub sources: Vec<String>,
+    pub violations: Vec<ViolationReport>,
+}
+
+impl ErrorReport {
+    /// Describes an error that occurred while using the given file.
+    pub fn new(err: &GetDogsError, path: Option<&str>) -> Self {
+        let root = err.root();
+        let (line, column) = position(root);
+
+        let context = match *err {
+            WithContext { ref frames, .. } => frames.iter().rev().cloned().collect(),
+            _ => Vec::new(),
+        };
+
+        let mut sources = Vec::new();
+        let mut source = root.source();
+        while let Some(e) = source {
+            sources.push(e.to_string());
+            source = e.source();
+        }
+
+        let violations = match *root {
+            Invalid(ref violations) => violations
+                .iter()
+                .map(|v| ViolationReport {
+                    index: v.index,
+                    field: v.field.to_string(),
+                    message: v.problem.to_string(),
+                })
+                .collect(),
+            // A bad field is reported like a violation
+            // so consumers find the dog and field in one place.
+            BadField {
+                index,
+                ref field,
+                ref source,
+            } => vec![ViolationReport {
+                index,
+                field: field.clone(),
+                message: source.to_string(),
+            }],
+            // Each duplicate is reported against the later dog.
+            Duplicates {
+                key,
+                ref duplicates,
+            } => duplicates
+                .iter()
+                .map(|d| ViolationReport {
+                    index: d.second,
+                    field: key.to_string(),
+                    message: d.to_string(),
+                })
+                .collect(),
+            UnknownBreeds(ref unknown) => unknown
+                .iter()
+                .map(|u| ViolationReport {
+                    index: u.index,
+                    field: "breed".to_string(),
+                    message: u.to_string(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        ErrorReport {
+            kind: err.kind(),
+            code: err.code(),
+            // The root's own message is reported rather than
+            // one that repeats the context frames.
+            message: root.to_string(),
+            path: path.map(String::from),
+            line,
+            column,
+            context,
+            sources,
+            violations,
+        }
+    }
+
+    /// Serializes the report as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        to_json_line(self)
+    }
+}
+
+// Reports only hold strings, numbers and lists of them,
+// which serde_json always serializes, so this cannot fail.
+pub(crate) fn to_json_line(report: &impl Serialize) -> String {
+    serde_json::to_string(report).expect("reports only hold strings and numbers")
+}
+
+fn position(err: &GetDogsError) -> (Option<usize>, Option<usize>) {
+    match *err {
+        // serde_json uses line 0 when there is no position.
+        // A streamed dog's own error counts from the start of the dog.
+        InStream { line, column, .. } => (Some(line), Some(column)),
+        BadJson(ref e) | BadField { source: ref e, .. } if e.line() > 0 => {
+            (Some(e.line()), Some(e.column()))
+        }
+        BadYaml(ref e) => match e.location() {
+            Some(location) => (Some(location.line()), Some(location.column())),
+            None => (None, None),
+        },
+        // Queries are a single line, and columns count from 1.
+        BadQuery(ref e) => (Some(1), Some(e.position + 1)),
+        BadCsv(ref e) => match e.position() {
+            Some(position) => (Some(position.line() as usize), None),
+            None => (None, None),
+        },
+        _ => (None, None),
+    }
+}
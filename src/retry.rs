@@ -0,0 +1,199 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use crate::{GetDogsError, MyResult};
+
+impl GetDogsError {
+    /// Whether the operation might succeed if it is tried again,
+    /// such as a read of a network file system that timed out.
+    ///
+    /// Errors about the contents of a file are never transient,
+    /// because reading the same file again gives the same result.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            GetDogsError::BadFile(e) => matches!(
+                e.kind(),
+                ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+            ),
+            GetDogsError::TimedOut(_) => true,
+            GetDogsError::WithContext { source, .. } | GetDogsError::InStream { source, .. } => {
+                source.is_transient()
+            }
+            // An operation that has already been retried isn't retried again.
+            _ => false,
+        }
+    }
+}
+
+/// When and how often to retry an operation that fails with a
+/// [transient](GetDogsError::is_transient) error.
+///
+/// The delay before each retry grows exponentially up to a maximum.
+/// Jitter makes each delay a random amount shorter, so that many processes
+/// that failed at the same moment don't all retry at the same moment too.
+///
+/// ```no_run
+/// use rust_error_handling::{get_dogs3, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::new()
+///     .with_max_attempts(5)
+///     .with_initial_delay(Duration::from_millis(50));
+/// let dogs = policy.run(|| get_dogs3("/mnt/kennel/dogs.json"));
+/// ```
+///
+/// When the last attempt fails, the error is [`GetDogsError::Retried`],
+/// which holds the error of every attempt.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_delay: Duration,
+    max_delay: Duration,
+    multiplier: f64,
+    jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Three attempts, waiting 100ms and then 200ms with jitter of half the delay.
+    pub fn new() -> Self {
+        RetryPolicy::default()
+    }
+
+    /// How many times the operation is run in total, including the first.
+    /// One means that it is never retried.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// The delay before the first retry.
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// The longest delay before a retry, however many there have been.
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// How much longer each delay is than the one before it.
+    /// Multipliers below 1 are raised to 1, so delays never shrink.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        // max also turns NaN into 1.
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// The largest fraction of each delay that is randomly taken off it,
+    /// from 0 for no jitter to 1 for a delay anywhere up to the backoff.
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        // clamp keeps NaN, which would make every delay NaN.
+        self.jitter = if jitter.is_nan() {
+            0.0
+        } else {
+            jitter.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    /// The delay before a retry before jitter is applied.
+    /// Retry 0 is the first retry, after the first attempt fails.
+    pub fn backoff(&self, retry: usize) -> Duration {
+        let exponent = retry.min(i32::MAX as usize) as i32;
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        // A delay too long for a Duration, including one that
+        // overflowed to infinity, is the maximum.
+        Duration::try_from_secs_f64(delay).map_or(self.max_delay, |d| d.min(self.max_delay))
+    }
+
+    /// Runs an operation until it succeeds, fails with an error
+    /// that isn't transient, or has been attempted the maximum number of times.
+    ///
+    /// An operation that fails on its first attempt without being retried
+    /// returns its error unchanged. Otherwise the error is
+    /// [`GetDogsError::Retried`], whose exit code, kind and HTTP status
+    /// are those of the last error.
+    pub fn run<T>(&self, mut operation: impl FnMut() -> MyResult<T>) -> MyResult<T> {
+        let mut earlier = Vec::new();
+        loop {
+            let err = match operation() {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            let attempt = earlier.len() + 1;
+            if !err.is_transient() || attempt >= self.max_attempts {
+                if earlier.is_empty() {
+                    return Err(err);
+                }
+                return Err(GetDogsError::Retried {
+                    earlier,
+                    source: Box::new(err),
+                });
+            }
+            let delay = self.jittered(self.backoff(earlier.len()));
+            tracing::warn!(
+                attempt,
+                kind = err.kind(),
+                delay_ms = delay.as_secs_f64() * 1000.0,
+                "retrying after {}",
+                err
+            );
+            earlier.push(err);
+            std::thread::sleep(delay);
+        }
+    }
+
+    // Rounding can take a delay near Duration::MAX out of range,
+    // in which case it is left as it was.
+    fn jittered(&self, delay: Duration) -> Duration {
+        let factor = 1.0 - self.jitter * random_fraction();
+        Duration::try_from_secs_f64(delay.as_secs_f64() * factor).unwrap_or(delay)
+    }
+}
+
+// This returns a number from 0 up to but not including 1.
+// std seeds the keys of RandomState randomly once per thread
+// and increments one of them for each new RandomState,
+// so hashing nothing gives a different number each time.
+// The numbers aren't independent, but they spread the delays out,
+// which is all that jitter needs, without a random number crate.
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    // The top 53 bits fill the mantissa of an f64 exactly.
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// The normal format describes the last error, which ended the retries.
+// The alternate format ({:#}) also lists the errors of the earlier attempts.
+pub(crate) fn fmt_attempts(
+    f: &mut fmt::Formatter,
+    earlier: &[GetDogsError],
+    source: &GetDogsError,
+) -> fmt::Result {
+    let attempts = earlier.len() + 1;
+    if !f.alternate() {
+        return write!(f, "failed after {} attempts: {}", attempts, source);
+    }
+    write!(f, "failed after {} attempts:", attempts)?;
+    for (i, e) in earlier.iter().chain(std::iter::once(source)).enumerate() {
+        write!(f, "\n  attempt {}: {}", i + 1, e)?;
+    }
+    Ok(())
+}
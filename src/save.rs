@@ -0,0 +1,112 @@
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::version::CURRENT_VERSION;
+use crate::{telemetry, Dog, GetDogsError, MyResult};
+
+/// Writes dogs to a JSON file, replacing it atomically.
+///
+/// The file is written in the current versioned format
+/// described by [`CURRENT_VERSION`].
+///
+/// The JSON is written to a temporary file in the same directory,
+/// flushed to disk, and then renamed over the target.
+/// A crash at any point leaves either the old file or the new one,
+/// never a partially written file.
+/// Each save uses its own temporary file,
+/// so concurrent saves of the same file don't corrupt each other
+/// and the last one to finish wins.
+/// Each step fails with its own GetDogsError variant.
+pub fn save_dogs(file_path: &str, dogs: &[Dog]) -> MyResult<()> {
+    telemetry::traced(telemetry::save(file_path, "json", dogs.len()), || {
+        let json = to_json(dogs).map_err(GetDogsError::CannotSerialize)?;
+        write_atomically(file_path, &json)
+    })
+}
+
+// This is shared with the savers for other formats.
+pub(crate) fn write_atomically(file_path: &str, contents: &[u8]) -> MyResult<()> {
+    telemetry::record_bytes(contents.len());
+    let target = Path::new(file_path);
+    let temp = temp_path(target);
+    // A file already at the temporary path belongs to someone else,
+    // so it is only removed after this save has created it.
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&temp)
+        .map_err(GetDogsError::CannotWrite)?;
+    if let Err(e) = write_synced(file, contents) {
+        let _ = fs::remove_file(&temp);
+        return Err(GetDogsError::CannotWrite(e));
+    }
+    if let Err(e) = fs::rename(&temp, target) {
+        let _ = fs::remove_file(&temp);
+        return Err(GetDogsError::CannotRename(e));
+    }
+    sync_parent(target).map_err(GetDogsError::CannotRename)
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    dogs: &'a [Dog],
+}
+
+// This uses the same four-space indentation as dogs.json
+// so saved files stay easy to edit by hand.
+pub(crate) fn to_json(dogs: &[Dog]) -> serde_json::Result<Vec<u8>> {
+    let mut json = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut json, formatter);
+    let envelope = Envelope {
+        version: CURRENT_VERSION,
+        dogs,
+    };
+    envelope.serialize(&mut serializer)?;
+    json.push(b'\n');
+    Ok(json)
+}
+
+// Counts the temporary files made by this process.
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+// The temporary file must be in the same directory as the target
+// because rename is only atomic within a single filesystem.
+// The process id keeps processes from colliding
+// and the counter keeps saves within one process from colliding.
+// The file is created with create_new, so if a file with the name
+// was left behind by a crash, the save fails instead of sharing it.
+pub(crate) fn temp_path(target: &Path) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let count = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    target.with_file_name(format!(".{}.{}.{}.tmp", name, std::process::id(), count))
+}
+
+fn write_synced(mut file: File, contents: &[u8]) -> std::io::Result<()> {
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+// On Unix the rename itself isn't durable until
+// the directory containing the file is flushed too.
+// Failing to flush it is reported as CannotRename,
+// although the new file is already in place.
+#[cfg(unix)]
+fn sync_parent(target: &Path) -> std::io::Result<()> {
+    match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_target: &Path) -> std::io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,353 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{get_dogs3, save_dogs, validate_dog, Dog, GetDogsError, MyResult};
+
+/// A place dogs are kept, looked up by name.
+///
+/// Every implementation reports the same failures the same way.
+/// Inserting a dog whose name is taken, or updating or deleting
+/// a dog that isn't there, fails with [`GetDogsError::Storage`].
+/// Dogs that break the validation rules are rejected
+/// with [`GetDogsError::Invalid`] before anything is stored.
+pub trait DogStore {
+    /// Returns every dog in the order they were inserted.
+    fn list(&self) -> MyResult<Vec<Dog>>;
+
+    /// Returns the dog with a given name, if there is one.
+    fn get(&self, name: &str) -> MyResult<Option<Dog>>;
+
+    /// Adds a dog whose name isn't already in the store.
+    fn insert(&mut self, dog: Dog) -> MyResult<()>;
+
+    /// Replaces the dog with the same name, keeping its position.
+    fn update(&mut self, dog: Dog) -> MyResult<()>;
+
+    /// Removes the dog with a given name.
+    fn delete(&mut self, name: &str) -> MyResult<()>;
+}
+
+/// What went wrong in a [`DogStore`].
+#[derive(Debug)]
+pub enum StoreProblem {
+    /// A dog with this name is already in the store.
+    Duplicate(String),
+    /// No dog with this name is in the store.
+    Missing(String),
+    /// The backend itself failed, such as a database error.
+    Backend(Box<dyn Error + Send + Sync>),
+}
+
+impl fmt::Display for StoreProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreProblem::Duplicate(name) => write!(f, "a dog named {:?} already exists", name),
+            StoreProblem::Missing(name) => write!(f, "no dog is named {:?}", name),
+            StoreProblem::Backend(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn storage(backend: &'static str, problem: StoreProblem) -> GetDogsError {
+    GetDogsError::Storage { backend, problem }
+}
+
+// The checks shared by every backend that keeps its dogs in a Vec.
+fn check_insert(backend: &'static str, dogs: &[Dog], dog: &Dog) -> MyResult<()> {
+    if dogs.iter().any(|d| d.name == dog.name) {
+        return Err(storage(backend, StoreProblem::Duplicate(dog.name.clone())));
+    }
+    check_valid(dogs.len(), dog)
+}
+
+fn position(backend: &'static str, dogs: &[Dog], name: &str) -> MyResult<usize> {
+    dogs.iter()
+        .position(|d| d.name == name)
+        .ok_or_else(|| storage(backend, StoreProblem::Missing(name.to_string())))
+}
+
+fn check_valid(index: usize, dog: &Dog) -> MyResult<()> {
+    let violations = validate_dog(index, dog);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(GetDogsError::Invalid(violations))
+    }
+}
+
+/// Keeps dogs in memory, which is handy for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    dogs: Vec<Dog>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a store holding some dogs.
+    /// They aren't validated or checked for duplicate names.
+    pub fn with_dogs(dogs: Vec<Dog>) -> Self {
+        MemoryStore { dogs }
+    }
+}
+
+impl DogStore for MemoryStore {
+    fn list(&self) -> MyResult<Vec<Dog>> {
+        Ok(self.dogs.clone())
+    }
+
+    fn get(&self, name: &str) -> MyResult<Option<Dog>> {
+        Ok(self.dogs.iter().find(|d| d.name == name).cloned())
+    }
+
+    fn insert(&mut self, dog: Dog) -> MyResult<()> {
+        check_insert("memory", &self.dogs, &dog)?;
+        self.dogs.push(dog);
+        Ok(())
+    }
+
+    fn update(&mut self, dog: Dog) -> MyResult<()> {
+        let index = position("memory", &self.dogs, &dog.name)?;
+        check_valid(index, &dog)?;
+        self.dogs[index] = dog;
+        Ok(())
+    }
+
+    fn delete(&mut self, name: &str) -> MyResult<()> {
+        let index = position("memory", &self.dogs, name)?;
+        self.dogs.remove(index);
+        Ok(())
+    }
+}
+
+/// Keeps dogs in a JSON file, read with [`get_dogs3`]
+/// and written with [`save_dogs`].
+///
+/// The file is read again for every operation
+/// so changes made by other programs are seen.
+/// A file that doesn't exist yet is an empty store
+/// and is created by the first insert, unless [`must_exist`] is used.
+///
+/// [`must_exist`]: JsonFileStore::must_exist
+#[derive(Debug)]
+pub struct JsonFileStore {
+    path: String,
+    must_exist: bool,
+}
+
+impl JsonFileStore {
+    pub fn new(path: impl Into<String>) -> Self {
+        JsonFileStore {
+            path: path.into(),
+            must_exist: false,
+        }
+    }
+
+    /// Makes a missing file fail with NotFound rather than being an empty store.
+    /// This suits long-running programs, where a missing file
+    /// more likely means it was moved than that it is new.
+    pub fn must_exist(mut self) -> Self {
+        self.must_exist = true;
+        self
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn load(&self) -> MyResult<Vec<Dog>> {
+        match get_dogs3(&self.path) {
+            Err(GetDogsError::NotFound(_)) if !self.must_exist => Ok(Vec::new()),
+            result => result,
+        }
+    }
+}
+
+impl DogStore for JsonFileStore {
+    fn list(&self) -> MyResult<Vec<Dog>> {
+        self.load()
+    }
+
+    fn get(&self, name: &str) -> MyResult<Option<Dog>> {
+        Ok(self.load()?.into_iter().find(|d| d.name == name))
+    }
+
+    fn insert(&mut self, dog: Dog) -> MyResult<()> {
+        let mut dogs = self.load()?;
+        check_insert("json", &dogs, &dog)?;
+        dogs.push(dog);
+        save_dogs(&self.path, &dogs)
+    }
+
+    fn update(&mut self, dog: Dog) -> MyResult<()> {
+        let mut dogs = self.load()?;
+        let index = position("json", &dogs, &dog.name)?;
+        check_valid(index, &dog)?;
+        dogs[index] = dog;
+        save_dogs(&self.path, &dogs)
+    }
+
+    fn delete(&mut self, name: &str) -> MyResult<()> {
+        let mut dogs = self.load()?;
+        let index = position("json", &dogs, name)?;
+        dogs.remove(index);
+        save_dogs(&self.path, &dogs)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+
+    use super::{check_valid, storage, DogStore, StoreProblem};
+    use crate::{Dog, GetDogsError, MyResult};
+
+    /// Keeps dogs in an SQLite database.
+    ///
+    /// Each dog is a row in the `dogs` table, which is created if needed.
+    /// The name has its own column so it can be unique,
+    /// and the whole dog is stored as JSON so new optional fields
+    /// don't need a change to the table.
+    pub struct SqliteStore {
+        connection: Connection,
+    }
+
+    fn backend(e: rusqlite::Error) -> GetDogsError {
+        storage("sqlite", StoreProblem::Backend(Box::new(e)))
+    }
+
+    impl SqliteStore {
+        /// Opens a database file, creating it if it doesn't exist.
+        pub fn open(path: &str) -> MyResult<Self> {
+            Self::init(Connection::open(path).map_err(backend)?)
+        }
+
+        /// Opens a database that only lasts as long as the store.
+        pub fn open_in_memory() -> MyResult<Self> {
+            Self::init(Connection::open_in_memory().map_err(backend)?)
+        }
+
+        fn init(connection: Connection) -> MyResult<Self> {
+            connection
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS dogs (
+                        id INTEGER PRIMARY KEY AUTOINCREMENT,
+                        name TEXT NOT NULL UNIQUE,
+                        dog TEXT NOT NULL
+                    )",
+                    [],
+                )
+                .map_err(backend)?;
+            Ok(SqliteStore { connection })
+        }
+
+        fn count(&self) -> MyResult<usize> {
+            self.connection
+                .query_row("SELECT COUNT(*) FROM dogs", [], |row| row.get(0))
+                .map_err(backend)
+        }
+    }
+
+    // Dogs read back from the database are deserialized
+    // the same way as dogs in a JSON file,
+    // so a corrupted row is reported as BadJson.
+    fn to_json(dog: &Dog) -> MyResult<String> {
+        serde_json::to_string(dog).map_err(GetDogsError::CannotSerialize)
+    }
+
+    impl DogStore for SqliteStore {
+        fn list(&self) -> MyResult<Vec<Dog>> {
+            let mut statement = self
+                .connection
+                .prepare("SELECT dog FROM dogs ORDER BY id")
+                .map_err(backend)?;
+            let rows = statement
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(backend)?;
+            let mut dogs = Vec::new();
+            for json in rows {
+                dogs.push(serde_json::from_str(&json.map_err(backend)?)?);
+            }
+            Ok(dogs)
+        }
+
+        fn get(&self, name: &str) -> MyResult<Option<Dog>> {
+            let json: Option<String> = self
+                .connection
+                .query_row("SELECT dog FROM dogs WHERE name = ?1", [name], |row| {
+                    row.get(0)
+                })
+                .optional()
+                .map_err(backend)?;
+            match json {
+                Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+                None => Ok(None),
+            }
+        }
+
+        fn insert(&mut self, dog: Dog) -> MyResult<()> {
+            // Duplicates are checked before validity, like check_insert does.
+            if self.get(&dog.name)?.is_some() {
+                return Err(storage("sqlite", StoreProblem::Duplicate(dog.name)));
+            }
+            check_valid(self.count()?, &dog)?;
+            let json = to_json(&dog)?;
+            // The unique name column still detects duplicates
+            // inserted by another connection since the check above.
+            match self.connection.execute(
+                "INSERT INTO dogs (name, dog) VALUES (?1, ?2)",
+                params![dog.name, json],
+            ) {
+                Ok(_) => Ok(()),
+                Err(rusqlite::Error::SqliteFailure(ref e, _))
+                    if e.code == ErrorCode::ConstraintViolation =>
+                {
+                    Err(storage("sqlite", StoreProblem::Duplicate(dog.name)))
+                }
+                Err(e) => Err(backend(e)),
+            }
+        }
+
+        fn update(&mut self, dog: Dog) -> MyResult<()> {
+            // The index used in violations is the position of the dog
+            // in the list, like the other stores.
+            let index: Option<usize> = self
+                .connection
+                .query_row(
+                    "SELECT (SELECT COUNT(*) FROM dogs AS d WHERE d.id < dogs.id)
+                     FROM dogs WHERE name = ?1",
+                    [&dog.name],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(backend)?;
+            let index =
+                index.ok_or_else(|| storage("sqlite", StoreProblem::Missing(dog.name.clone())))?;
+            check_valid(index, &dog)?;
+            let json = to_json(&dog)?;
+            self.connection
+                .execute(
+                    "UPDATE dogs SET dog = ?2 WHERE name = ?1",
+                    params![dog.name, json],
+                )
+                .map_err(backend)?;
+            Ok(())
+        }
+
+        fn delete(&mut self, name: &str) -> MyResult<()> {
+            let changed = self
+                .connection
+                .execute("DELETE FROM dogs WHERE name = ?1", [name])
+                .map_err(backend)?;
+            if changed == 0 {
+                return Err(storage("sqlite", StoreProblem::Missing(name.to_string())));
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,552 @@
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::time::Instant;
+use tracing::Span;
+
+use crate::telemetry::{self, Operation};
+use crate::validate::validate_dog;
+use crate::version::{upgrade, version_of, CURRENT_VERSION};
+use crate::{json, Dog, GetDogsError, MyResult};
+
+/// Reads dogs one at a time from a JSON array or from
+/// newline-delimited JSON (NDJSON) with one dog per line.
+///
+/// The format is detected from the first non-whitespace character.
+/// Versioned documents written by save_dogs, which are objects
+/// whose first key is "version", are read too, streaming their array of dogs.
+/// Older versions can't be upgraded without reading the whole document,
+/// so they produce the error that get_dogs3 gives for them.
+/// Only one dog is held in memory at a time,
+/// so files too large to read into a String can be processed.
+/// Each dog is validated like in get_dogs3.
+///
+/// A dog that can't be parsed or is invalid produces an Err item
+/// and iteration continues with the next dog.
+/// Read failures and broken array syntax, including a trailing comma
+/// or text after the closing bracket, end the iteration
+/// after producing an Err item.
+///
+/// Parse errors are wrapped in [`GetDogsError::InStream`],
+/// which gives the index of the dog and the line and column in the whole input,
+/// because the positions serde_json reports count from the start of each dog.
+///
+/// The stream is traced and counted as a load like the other loaders,
+/// with errors for single dogs reported as warnings.
+/// The load ends when the iteration does,
+/// so a stream dropped before its end isn't counted.
+pub fn stream_dogs<R: Read>(reader: R) -> DogStream<BufReader<R>> {
+    DogStream::new(BufReader::new(reader))
+}
+
+/// What is wrong with the JSON around the dogs read by [`stream_dogs`],
+/// reported as [`GetDogsError::BadSyntax`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxProblem {
+    /// Something else was found where the named JSON was expected.
+    Expected(&'static str),
+    /// The array has a comma after its last dog.
+    TrailingComma,
+    /// There is more than whitespace after the array.
+    TrailingText,
+    /// The input ends inside the array.
+    Truncated,
+    /// A versioned document has its dogs before its version,
+    /// which must be known before the dogs can be read.
+    VersionAfterDogs,
+}
+
+impl fmt::Display for SyntaxProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyntaxProblem::Expected(what) => write!(f, "expected {}", what),
+            SyntaxProblem::TrailingComma => write!(f, "trailing comma after the last dog"),
+            SyntaxProblem::TrailingText => write!(f, "text after the end of the dogs"),
+            SyntaxProblem::Truncated => write!(f, "the input ends before the dogs do"),
+            SyntaxProblem::VersionAfterDogs => {
+                write!(f, "the version must come before the dogs to stream them")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Start,
+    Array,
+    // The closing bracket of the array has been read.
+    End,
+    Lines,
+    Done,
+}
+
+/// The iterator returned by [`stream_dogs`].
+pub struct DogStream<R> {
+    reader: R,
+    state: State,
+    index: usize,
+    // Whether the array is the dogs of a versioned document,
+    // so the closing brace of the document must follow it.
+    envelope: bool,
+    // This holds the text of the current dog and is reused for each one.
+    buffer: Vec<u8>,
+    // The 1-based line and column of the next byte to be read,
+    // and of the first byte in the buffer.
+    line: usize,
+    column: usize,
+    start: (usize, usize),
+    // The load this stream is traced as, until it ends.
+    // Streams read by another loader aren't traced again.
+    operation: Option<(Operation, Instant)>,
+    bytes: usize,
+    dogs: usize,
+}
+
+impl<R: BufRead> DogStream<R> {
+    /// Creates a stream from a reader that is already buffered.
+    pub fn new(reader: R) -> Self {
+        DogStream {
+            operation: Some((telemetry::stream(), Instant::now())),
+            ..DogStream::untraced(reader)
+        }
+    }
+
+    // This is for loaders that already run in their own span.
+    pub(crate) fn untraced(reader: R) -> Self {
+        DogStream {
+            reader,
+            state: State::Start,
+            index: 0,
+            envelope: false,
+            buffer: Vec::new(),
+            line: 1,
+            column: 1,
+            start: (1, 1),
+            operation: None,
+            bytes: 0,
+            dogs: 0,
+        }
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(bytes) => return Ok(bytes.first().copied()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // This consumes a byte that has been peeked and moves the position past it.
+    fn consume(&mut self, byte: u8) {
+        self.reader.consume(1);
+        self.bytes += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        let byte = self.peek()?;
+        if let Some(byte) = byte {
+            self.consume(byte);
+        }
+        Ok(byte)
+    }
+
+    fn skip_whitespace(&mut self) -> io::Result<Option<u8>> {
+        while let Some(byte) = self.peek()? {
+            if !byte.is_ascii_whitespace() {
+                return Ok(Some(byte));
+            }
+            self.consume(byte);
+        }
+        Ok(None)
+    }
+
+    // This skips whitespace and reads the given text,
+    // returning whether it was there.
+    fn read_text(&mut self, text: &[u8]) -> io::Result<bool> {
+        self.skip_whitespace()?;
+        for &expected in text {
+            if self.peek()? != Some(expected) {
+                return Ok(false);
+            }
+            self.consume(expected);
+        }
+        Ok(true)
+    }
+
+    // This reads a number or other value without structure,
+    // up to the next comma, brace or whitespace.
+    fn read_scalar(&mut self) -> io::Result<Vec<u8>> {
+        self.skip_whitespace()?;
+        let mut value = Vec::new();
+        while let Some(byte) = self.peek()? {
+            if byte.is_ascii_whitespace() || byte == b',' || byte == b'}' {
+                break;
+            }
+            self.consume(byte);
+            value.push(byte);
+        }
+        Ok(value)
+    }
+
+    // This reads the rest of the current line into the buffer,
+    // returning the number of bytes read.
+    fn finish_line(&mut self) -> io::Result<usize> {
+        let read = self.reader.read_until(b'\n', &mut self.buffer)?;
+        self.bytes += read;
+        if read > 0 && self.buffer.ends_with(b"\n") {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += read;
+        }
+        Ok(read)
+    }
+
+    // This reads the opening brace of an object and its first key into the buffer,
+    // returning the key, or None if the object doesn't start with one.
+    fn read_first_key(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.buffer.clear();
+        self.start = (self.line, self.column);
+        self.consume(b'{');
+        self.buffer.push(b'{');
+        loop {
+            match self.peek()? {
+                Some(byte) if byte.is_ascii_whitespace() => {
+                    self.consume(byte);
+                    self.buffer.push(byte);
+                }
+                Some(b'"') => break,
+                _ => return Ok(None),
+            }
+        }
+        self.consume(b'"');
+        self.buffer.push(b'"');
+        let key_start = self.buffer.len();
+        let mut escaped = false;
+        while let Some(byte) = self.next_byte()? {
+            self.buffer.push(byte);
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                let key_end = self.buffer.len() - 1;
+                return Ok(Some(self.buffer[key_start..key_end].to_vec()));
+            }
+        }
+        Ok(None)
+    }
+
+    // A dog never has a "version" or "dogs" field, so an object
+    // with one of those first is a versioned document and
+    // any other object is the first dog of NDJSON.
+    fn start_object(&mut self) -> io::Result<Option<MyResult<Dog>>> {
+        match self.read_first_key()?.as_deref() {
+            Some(b"version") => {
+                self.record_format("json");
+                self.start_envelope()
+            }
+            Some(b"dogs") => {
+                self.state = State::Done;
+                let problem = SyntaxProblem::VersionAfterDogs;
+                Ok(Some(Err(self.syntax_error(None, problem))))
+            }
+            // The bytes read so far begin the first line.
+            _ => {
+                self.record_format("ndjson");
+                self.state = State::Lines;
+                self.finish_line()?;
+                Ok(Some(self.parse_buffer()))
+            }
+        }
+    }
+
+    // This reads a versioned document up to the opening bracket
+    // of its dogs, which are then read like any other array.
+    fn start_envelope(&mut self) -> io::Result<Option<MyResult<Dog>>> {
+        self.state = State::Done;
+        if !self.read_text(b":")? {
+            return Ok(Some(Err(
+                self.syntax_error(None, SyntaxProblem::Expected("`:`"))
+            )));
+        }
+        let version = match serde_json::from_slice(&self.read_scalar()?) {
+            Ok(version) => version,
+            Err(_) => {
+                let problem = SyntaxProblem::Expected("a version number");
+                return Ok(Some(Err(self.syntax_error(None, problem))));
+            }
+        };
+        if let Err(e) = check_version(version) {
+            return Ok(Some(Err(e)));
+        }
+        let rest: [(&[u8], &'static str); 4] = [
+            (b",", "`,`"),
+            (b"\"dogs\"", "`\"dogs\"`"),
+            (b":", "`:`"),
+            (b"[", "`[`"),
+        ];
+        for (text, what) in rest {
+            if !self.read_text(text)? {
+                let problem = SyntaxProblem::Expected(what);
+                return Ok(Some(Err(self.syntax_error(None, problem))));
+            }
+        }
+        self.envelope = true;
+        self.state = State::Array;
+        self.advance()
+    }
+
+    // This copies the text of the next array element into the buffer.
+    // Commas and closing brackets inside strings and nested values
+    // don't end the element, so the depth and string state are tracked.
+    // It returns true if the closing bracket of the array was reached.
+    fn read_element(&mut self) -> io::Result<bool> {
+        self.buffer.clear();
+        self.start = (self.line, self.column);
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        while let Some(byte) = self.next_byte()? {
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                }
+            } else {
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' if depth > 0 => depth -= 1,
+                    b',' if depth == 0 => return Ok(false),
+                    b']' => return Ok(true),
+                    _ => {}
+                }
+            }
+            self.buffer.push(byte);
+        }
+        Err(io::ErrorKind::UnexpectedEof.into())
+    }
+
+    // This parses and validates the dog in the buffer.
+    fn parse_buffer(&mut self) -> MyResult<Dog> {
+        let index = self.index;
+        self.index += 1;
+        let dog = json::dog_from_slice(index, &self.buffer).map_err(|e| self.locate(index, e))?;
+        let violations = validate_dog(index, &dog);
+        if violations.is_empty() {
+            Ok(dog)
+        } else {
+            Err(GetDogsError::Invalid(violations))
+        }
+    }
+
+    // This gives a parse error of the dog in the buffer its index
+    // and its position in the whole input.
+    fn locate(&self, index: usize, err: GetDogsError) -> GetDogsError {
+        let (start_line, start_column) = self.start;
+        let (line, column) = match err {
+            // serde_json uses line 0 when there is no position.
+            GetDogsError::BadJson(ref e) | GetDogsError::BadField { source: ref e, .. }
+                if e.line() > 0 =>
+            {
+                // Only the first line of the dog is offset by its starting column.
+                if e.line() == 1 {
+                    (start_line, start_column + e.column() - 1)
+                } else {
+                    (start_line + e.line() - 1, e.column())
+                }
+            }
+            _ => self.start,
+        };
+        GetDogsError::InStream {
+            index: Some(index),
+            line,
+            column,
+            source: Box::new(err),
+        }
+    }
+
+    // This reports broken syntax at the position of the next byte.
+    fn syntax_error(&self, index: Option<usize>, problem: SyntaxProblem) -> GetDogsError {
+        GetDogsError::InStream {
+            index,
+            line: self.line,
+            column: self.column,
+            source: Box::new(GetDogsError::BadSyntax(problem)),
+        }
+    }
+
+    fn next_in_array(&mut self) -> io::Result<Option<MyResult<Dog>>> {
+        if self.skip_whitespace()? == Some(b']') {
+            // Only an empty array can end where a dog is expected.
+            if self.index > 0 {
+                self.state = State::Done;
+                return Ok(Some(Err(
+                    self.syntax_error(None, SyntaxProblem::TrailingComma)
+                )));
+            }
+            self.consume(b']');
+            self.state = State::End;
+            return self.advance();
+        }
+        let ended = match self.read_element() {
+            Ok(ended) => ended,
+            // Report a truncated array as a JSON syntax error.
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                self.state = State::Done;
+                return Ok(Some(Err(
+                    self.syntax_error(Some(self.index), SyntaxProblem::Truncated)
+                )));
+            }
+            Err(e) => return Err(e),
+        };
+        if ended {
+            self.state = State::End;
+        }
+        Ok(Some(self.parse_buffer()))
+    }
+
+    // Only whitespace may follow the closing bracket,
+    // or the closing brace of a versioned document.
+    fn after_array(&mut self) -> io::Result<Option<MyResult<Dog>>> {
+        self.state = State::Done;
+        if self.envelope && !self.read_text(b"}")? {
+            return Ok(Some(Err(
+                self.syntax_error(None, SyntaxProblem::Expected("`}`"))
+            )));
+        }
+        match self.skip_whitespace()? {
+            None => Ok(None),
+            Some(_) => Ok(Some(Err(
+                self.syntax_error(None, SyntaxProblem::TrailingText)
+            ))),
+        }
+    }
+
+    fn next_line(&mut self) -> io::Result<Option<MyResult<Dog>>> {
+        // Blank lines between dogs are ignored.
+        loop {
+            self.buffer.clear();
+            self.start = (self.line, self.column);
+            if self.finish_line()? == 0 {
+                self.state = State::Done;
+                return Ok(None);
+            }
+            if !self.buffer.iter().all(u8::is_ascii_whitespace) {
+                return Ok(Some(self.parse_buffer()));
+            }
+        }
+    }
+
+    fn record_format(&self, format: &str) {
+        if let Some((operation, _)) = &self.operation {
+            operation.span.record("format", format);
+        }
+    }
+
+    // Errors for single dogs are reported as they are produced.
+    // The load is finished by the first error that ends the stream, or by its end.
+    fn observe(&mut self, item: &Option<MyResult<Dog>>) {
+        match item {
+            Some(Ok(_)) => {
+                self.dogs += 1;
+                return;
+            }
+            Some(Err(e)) if self.state != State::Done => {
+                if let Some((operation, _)) = &self.operation {
+                    let index = self.index - 1;
+                    operation
+                        .span
+                        .in_scope(|| telemetry::error_event(e, Some(index)));
+                }
+                return;
+            }
+            _ => {}
+        }
+        if let Some((operation, start)) = self.operation.take() {
+            operation.span.record("bytes", self.bytes);
+            operation.span.record("dogs", self.dogs);
+            match item {
+                Some(result) => telemetry::finish(&operation, start, result),
+                None => telemetry::finish(&operation, start, &Ok(())),
+            }
+        }
+    }
+
+    fn advance(&mut self) -> io::Result<Option<MyResult<Dog>>> {
+        match self.state {
+            State::Start => match self.skip_whitespace()? {
+                None => {
+                    self.state = State::Done;
+                    Ok(None)
+                }
+                Some(b'[') => {
+                    self.record_format("json");
+                    self.consume(b'[');
+                    self.state = State::Array;
+                    self.advance()
+                }
+                Some(b'{') => self.start_object(),
+                Some(_) => {
+                    self.state = State::Done;
+                    let expected = SyntaxProblem::Expected("`[` or `{`");
+                    Ok(Some(Err(self.syntax_error(None, expected))))
+                }
+            },
+            State::Array => self.next_in_array(),
+            State::End => self.after_array(),
+            State::Lines => self.next_line(),
+            State::Done => Ok(None),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for DogStream<R> {
+    type Item = MyResult<Dog>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let span = match &self.operation {
+            Some((operation, _)) => operation.span.clone(),
+            None => Span::none(),
+        };
+        let item = span.in_scope(|| match self.advance() {
+            Ok(item) => item,
+            Err(e) => {
+                self.state = State::Done;
+                Some(Err(GetDogsError::BadFile(e)))
+            }
+        });
+        self.observe(&item);
+        item
+    }
+}
+
+// Migrations work on whole documents, so only the current version
+// can be streamed. Older versions fail like they do in get_dogs3
+// when their migrations fail, as they do for objects of version 1.
+fn check_version(version: Value) -> MyResult<()> {
+    let doc = json!({ "version": version });
+    match version_of(&doc)? {
+        Some(version) if version < CURRENT_VERSION => {
+            upgrade(doc, version)?;
+            Err(GetDogsError::MigrationFailed {
+                from: version,
+                to: CURRENT_VERSION,
+                reason: "an older versioned document can't be streamed".to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
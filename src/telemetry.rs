@@ -0,0 +1,130 @@
+// The loaders and savers report what they do with the tracing crate.
+// Each load or save runs in a span with these fields:
+//
+// - path: the file being read or written, except for streams
+// - format: the file format, such as "json"
+// - bytes: the size of the file, once it has been read or encoded
+// - dogs: the number of dogs loaded or saved
+// - duration_ms: how long the operation took
+//
+// Every error returned by a loader or saver is also reported
+// as an event in its span with the error's kind, code and source chain,
+// as is every bad dog collected by a lenient load.
+// Nothing is recorded unless the application installs a subscriber.
+//
+// With the metrics feature, each load and save is also counted
+// in Metrics::global, whether or not there is a subscriber.
+
+use std::fmt::Display;
+use std::time::Instant;
+use tracing::field::Empty;
+use tracing::{info_span, Span};
+
+use crate::{ErrorReport, GetDogsError, MyResult};
+
+// The name is kept separately because a span has no name
+// when there is no subscriber interested in it.
+pub(crate) struct Operation {
+    // This is only needed to count the operation.
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub name: &'static str,
+    pub span: Span,
+}
+
+pub(crate) fn load(path: &str, format: impl Display) -> Operation {
+    Operation {
+        name: "load",
+        span: info_span!(
+            "load",
+            path,
+            format = %format,
+            bytes = Empty,
+            dogs = Empty,
+            duration_ms = Empty
+        ),
+    }
+}
+
+// Streams have no path, and their format is recorded
+// once the first byte shows whether they are JSON or NDJSON.
+pub(crate) fn stream() -> Operation {
+    Operation {
+        name: "load",
+        span: info_span!(
+            "load",
+            path = Empty,
+            format = Empty,
+            bytes = Empty,
+            dogs = Empty,
+            duration_ms = Empty
+        ),
+    }
+}
+
+pub(crate) fn save(path: &str, format: impl Display, dogs: usize) -> Operation {
+    Operation {
+        name: "save",
+        span: info_span!(
+            "save",
+            path,
+            format = %format,
+            bytes = Empty,
+            dogs,
+            duration_ms = Empty
+        ),
+    }
+}
+
+/// Runs an operation in its span, recording how long it took
+/// and reporting the error if it fails.
+pub(crate) fn traced<T>(operation: Operation, run: impl FnOnce() -> MyResult<T>) -> MyResult<T> {
+    let start = Instant::now();
+    let result = operation.span.in_scope(run);
+    finish(&operation, start, &result);
+    result
+}
+
+// This is separate from traced so async code,
+// which can't run inside in_scope, can use it too.
+pub(crate) fn finish<T>(operation: &Operation, start: Instant, result: &MyResult<T>) {
+    let span = &operation.span;
+    span.record("duration_ms", start.elapsed().as_secs_f64() * 1000.0);
+    if let Err(e) = result {
+        span.in_scope(|| error_event(e, None));
+    }
+    #[cfg(feature = "metrics")]
+    crate::Metrics::global().record(operation.name, result);
+}
+
+// These record on the current span, so they do nothing
+// when called outside of a load or save.
+pub(crate) fn record_bytes(bytes: usize) {
+    Span::current().record("bytes", bytes);
+}
+
+pub(crate) fn record_dogs(dogs: usize) {
+    Span::current().record("dogs", dogs);
+}
+
+/// Reports an error with its variant and source chain.
+/// The index is that of the dog it belongs to, if any.
+pub(crate) fn error_event(err: &GetDogsError, index: Option<usize>) {
+    let report = ErrorReport::new(err, None);
+    match index {
+        Some(index) => tracing::warn!(
+            kind = report.kind,
+            code = report.code,
+            index,
+            sources = ?report.sources,
+            "{}",
+            report.message
+        ),
+        None => tracing::error!(
+            kind = report.kind,
+            code = report.code,
+            sources = ?report.sources,
+            "{}",
+            report.message
+        ),
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Dog, DogStore, GetDogsError, MyResult};
+
+/// What makes two dogs the same dog.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniqueKey {
+    Name,
+    NameAndBreed,
+    /// Dogs without a microchip are never duplicates by this key.
+    Microchip,
+}
+
+impl UniqueKey {
+    // Returns None for dogs that the key doesn't apply to.
+    fn value(self, dog: &Dog) -> Option<String> {
+        match self {
+            UniqueKey::Name => Some(dog.name().to_string()),
+            UniqueKey::NameAndBreed => Some(format!("{} ({})", dog.name(), dog.breed())),
+            UniqueKey::Microchip => dog.microchip().map(String::from),
+        }
+    }
+}
+
+impl fmt::Display for UniqueKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            UniqueKey::Name => "name",
+            UniqueKey::NameAndBreed => "name+breed",
+            UniqueKey::Microchip => "microchip",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for UniqueKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(UniqueKey::Name),
+            "name+breed" => Ok(UniqueKey::NameAndBreed),
+            "microchip" => Ok(UniqueKey::Microchip),
+            _ => Err(format!(
+                "unknown key {:?}, expected name, name+breed or microchip",
+                s
+            )),
+        }
+    }
+}
+
+/// A dog with the same key as an earlier dog.
+/// The indices are positions in the array,
+/// and the value is the key they share.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Duplicate {
+    pub first: usize,
+    pub second: usize,
+    pub value: String,
+}
+
+impl fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "dogs {} and {} are both {:?}",
+            self.first, self.second, self.value
+        )
+    }
+}
+
+/// Finds every dog with the same key as an earlier one.
+/// Each is paired with the first dog that has the key,
+/// so three copies of a dog are reported as two duplicates.
+pub fn find_duplicates(dogs: &[Dog], key: UniqueKey) -> Vec<Duplicate> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (index, dog) in dogs.iter().enumerate() {
+        if let Some(value) = key.value(dog) {
+            match seen.get(&value) {
+                Some(&first) => duplicates.push(Duplicate {
+                    first,
+                    second: index,
+                    value,
+                }),
+                None => {
+                    seen.insert(value, index);
+                }
+            }
+        }
+    }
+    duplicates
+}
+
+/// Fails with [`GetDogsError::Duplicates`] listing every duplicate.
+pub fn check_unique(dogs: &[Dog], key: UniqueKey) -> MyResult<()> {
+    let duplicates = find_duplicates(dogs, key);
+    if duplicates.is_empty() {
+        Ok(())
+    } else {
+        Err(GetDogsError::Duplicates { key, duplicates })
+    }
+}
+
+/// How [`dedupe`] combines dogs that have the same key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DedupeStrategy {
+    /// Keep the first dog and drop the rest.
+    KeepFirst,
+    /// Keep the last dog, in the position of the first.
+    KeepLast,
+    /// Keep the first dog, filling its missing optional fields
+    /// from the later dogs in order.
+    /// Fields the first dog has are never replaced.
+    Merge,
+}
+
+impl FromStr for DedupeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(DedupeStrategy::KeepFirst),
+            "last" => Ok(DedupeStrategy::KeepLast),
+            "merge" => Ok(DedupeStrategy::Merge),
+            _ => Err(format!(
+                "unknown strategy {:?}, expected first, last or merge",
+                s
+            )),
+        }
+    }
+}
+
+/// Combines dogs that have the same key into one,
+/// keeping the dogs in the order they first appear.
+pub fn dedupe(dogs: Vec<Dog>, key: UniqueKey, strategy: DedupeStrategy) -> Vec<Dog> {
+    let mut kept: Vec<Dog> = Vec::with_capacity(dogs.len());
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for dog in dogs {
+        let value = key.value(&dog);
+        match value.as_ref().and_then(|v| positions.get(v)) {
+            Some(&position) => match strategy {
+                DedupeStrategy::KeepFirst => {}
+                DedupeStrategy::KeepLast => kept[position] = dog,
+                DedupeStrategy::Merge => kept[position].fill_missing(&dog),
+            },
+            None => {
+                if let Some(value) = value {
+                    positions.insert(value, kept.len());
+                }
+                kept.push(dog);
+            }
+        }
+    }
+    kept
+}
+
+/// A [`DogStore`] that rejects dogs with the same key as another.
+///
+/// Listing fails with [`GetDogsError::Duplicates`] if the
+/// underlying store already holds duplicates, such as a file edited by hand.
+/// Inserting or updating a dog fails the same way
+/// if it would create a duplicate, and nothing is changed.
+pub struct UniqueStore<S> {
+    store: S,
+    key: UniqueKey,
+}
+
+impl<S: DogStore> UniqueStore<S> {
+    pub fn new(store: S, key: UniqueKey) -> Self {
+        UniqueStore { store, key }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    // Checks the dogs as they would be after a change.
+    fn check_change(&self, dog: &Dog, replacing: Option<&str>) -> MyResult<()> {
+        let mut dogs = self.store.list()?;
+        match replacing.and_then(|name| dogs.iter().position(|d| d.name() == name)) {
+            Some(index) => dogs[index] = dog.clone(),
+            None => dogs.push(dog.clone()),
+        }
+        check_unique(&dogs, self.key)
+    }
+}
+
+impl<S: DogStore> DogStore for UniqueStore<S> {
+    fn list(&self) -> MyResult<Vec<Dog>> {
+        let dogs = self.store.list()?;
+        check_unique(&dogs, self.key)?;
+        Ok(dogs)
+    }
+
+    fn get(&self, name: &str) -> MyResult<Option<Dog>> {
+        self.store.get(name)
+    }
+
+    fn insert(&mut self, dog: Dog) -> MyResult<()> {
+        self.check_change(&dog, None)?;
+        self.store.insert(dog)
+    }
+
+    fn update(&mut self, dog: Dog) -> MyResult<()> {
+        self.check_change(&dog, Some(dog.name()))?;
+        self.store.update(dog)
+    }
+
+    fn delete(&mut self, name: &str) -> MyResult<()> {
+        self.store.delete(name)
+    }
+}
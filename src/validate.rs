@@ -0,0 +1,107 @@
+use std::fmt;
+
+use crate::{Dog, GetDogsError, MyResult};
+
+// These limits are measured in characters, not bytes.
+const MAX_NAME_LEN: usize = 50;
+const MAX_BREED_LEN: usize = 100;
+
+/// This describes a single way in which a field value breaks the rules.
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+    Empty,
+    Untrimmed,
+    TooLong { len: usize, max: usize },
+    BadCharacter(char),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::Empty => write!(f, "must not be empty"),
+            Problem::Untrimmed => write!(f, "must not start or end with whitespace"),
+            Problem::TooLong { len, max } => {
+                write!(f, "is {} characters long, but the maximum is {}", len, max)
+            }
+            Problem::BadCharacter(c) => write!(f, "contains the character {:?}", c),
+        }
+    }
+}
+
+/// This identifies which dog and which field a problem was found in.
+/// The index is the position of the dog in the JSON array.
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub index: usize,
+    pub field: &'static str,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "dog {} {} {}", self.index, self.field, self.problem)
+    }
+}
+
+/// This checks every dog and returns all the violations found
+/// rather than stopping at the first one,
+/// so a file can be fixed in a single pass.
+pub fn validate_dogs(dogs: &[Dog]) -> MyResult<()> {
+    let violations: Vec<Violation> = dogs
+        .iter()
+        .enumerate()
+        .flat_map(|(index, dog)| validate_dog(index, dog))
+        .collect();
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(GetDogsError::Invalid(violations))
+    }
+}
+
+/// This checks a single dog that is at the given index in the JSON array.
+pub fn validate_dog(index: usize, dog: &Dog) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    check_field(index, "name", &dog.name, MAX_NAME_LEN, &mut violations);
+    check_field(index, "breed", &dog.breed, MAX_BREED_LEN, &mut violations);
+    violations
+}
+
+fn check_field(
+    index: usize,
+    field: &'static str,
+    value: &str,
+    max: usize,
+    violations: &mut Vec<Violation>,
+) {
+    let mut report = |problem| {
+        violations.push(Violation {
+            index,
+            field,
+            problem,
+        })
+    };
+
+    if value.trim().is_empty() {
+        report(Problem::Empty);
+        return;
+    }
+    if value.trim() != value {
+        report(Problem::Untrimmed);
+    }
+    let len = value.chars().count();
+    if len > max {
+        report(Problem::TooLong { len, max });
+    }
+    // Only the first disallowed character is reported
+    // so a badly encoded value doesn't produce a flood of violations.
+    if let Some(c) = value.chars().find(|&c| !is_allowed(c)) {
+        report(Problem::BadCharacter(c));
+    }
+}
+
+// Names and breeds like "Mr. O'Malley" and "Jack Russell-Terrier"
+// are allowed, but control characters and symbols are not.
+fn is_allowed(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, ' ' | '-' | '\'' | '.')
+}
@@ -0,0 +1,93 @@
+use serde_json::{json, Value};
+
+use crate::{GetDogsError, MyResult};
+
+/// The version of the file format written by [`save_dogs`].
+///
+/// Version 1 files are bare JSON arrays of dogs.
+/// Version 2 files are objects with a version and the array of dogs:
+///
+/// ```json
+/// { "version": 2, "dogs": [{ "name": "Comet", "breed": "Whippet" }] }
+/// ```
+///
+/// [`save_dogs`]: crate::save_dogs
+pub const CURRENT_VERSION: u32 = 2;
+
+/// A step that upgrades a JSON document from one version to the next.
+pub struct Migration {
+    /// The version this migration upgrades from.
+    /// It produces a document of version `from + 1`.
+    pub from: u32,
+    pub description: &'static str,
+    /// Returns a message describing why the document can't be upgraded.
+    pub apply: fn(Value) -> Result<Value, String>,
+}
+
+// When the format changes, increment CURRENT_VERSION
+// and add a migration from the previous version here.
+// Old files are upgraded by applying each migration in turn.
+static MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "wrap the array of dogs in a versioned object",
+    apply: wrap_array,
+}];
+
+/// The registered migrations, ordered by the version they upgrade from.
+pub fn migrations() -> &'static [Migration] {
+    MIGRATIONS
+}
+
+fn wrap_array(doc: Value) -> Result<Value, String> {
+    match doc {
+        Value::Array(dogs) => Ok(json!({ "version": 2, "dogs": dogs })),
+        _ => Err("a version 1 document must be an array".to_string()),
+    }
+}
+
+// Bare arrays are version 1 and other documents must say what they are.
+// A document that is neither an array nor an object
+// is left for deserialization to report as BadJson.
+pub(crate) fn version_of(doc: &Value) -> MyResult<Option<u32>> {
+    let object = match doc {
+        Value::Array(_) => return Ok(Some(1)),
+        Value::Object(object) => object,
+        _ => return Ok(None),
+    };
+    match object.get("version") {
+        Some(Value::Number(n)) => match n.as_u64() {
+            Some(v) if (1..=CURRENT_VERSION as u64).contains(&v) => Ok(Some(v as u32)),
+            _ => Err(GetDogsError::UnsupportedVersion(n.to_string())),
+        },
+        Some(other) => Err(GetDogsError::UnsupportedVersion(other.to_string())),
+        None => Err(GetDogsError::UnsupportedVersion("missing".to_string())),
+    }
+}
+
+// This applies migrations one version at a time until
+// the document is at the current version.
+pub(crate) fn upgrade(mut doc: Value, mut version: u32) -> MyResult<Value> {
+    while version < CURRENT_VERSION {
+        let failed = |reason: String| GetDogsError::MigrationFailed {
+            from: version,
+            to: version + 1,
+            reason,
+        };
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.from == version)
+            .ok_or_else(|| failed("no migration is registered".to_string()))?;
+        doc = (migration.apply)(doc).map_err(failed)?;
+        version += 1;
+    }
+    Ok(doc)
+}
+
+// This returns the array of dogs in a document of any version.
+pub(crate) fn dogs_of(doc: &Value) -> Option<&Value> {
+    match doc {
+        Value::Array(_) => Some(doc),
+        Value::Object(object) => object.get("dogs"),
+        _ => None,
+    }
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "async")]
+
+use rust_error_handling::{
+    cancellable, get_dogs_async, save_dogs_async, timeout, CancelToken, GetDogsError,
+};
+use std::fs;
+use std::time::Duration;
+
+mod common;
+use common::{dogs, temp_dir};
+
+#[tokio::test]
+async fn async_loader_matches_get_dogs3() {
+    assert_eq!(
+        get_dogs_async("tests/fixtures/dogs.json").await.unwrap(),
+        dogs()
+    );
+    assert!(matches!(
+        get_dogs_async("tests/fixtures/missing.json").await,
+        Err(GetDogsError::NotFound(_))
+    ));
+    assert!(matches!(
+        get_dogs_async("tests/fixtures/malformed.json").await,
+        Err(GetDogsError::BadJson(_))
+    ));
+    assert!(matches!(
+        get_dogs_async("tests/fixtures/invalid.json").await,
+        Err(GetDogsError::Invalid(_))
+    ));
+}
+
+#[tokio::test]
+async fn saved_dogs_load_again() {
+    let dir = temp_dir("round-trip");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+    save_dogs_async(path, &dogs()).await.unwrap();
+    assert_eq!(get_dogs_async(path).await.unwrap(), dogs());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_saves_of_one_file_dont_collide() {
+    let dir = temp_dir("concurrent");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+    let dogs = dogs();
+    let (first, second) = tokio::join!(
+        save_dogs_async(path, &dogs),
+        save_dogs_async(path, &dogs[..1])
+    );
+    first.unwrap();
+    second.unwrap();
+    // Whichever save finished last wrote a whole file.
+    assert!(!get_dogs_async(path).await.unwrap().is_empty());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn dropped_saves_leave_a_whole_file() {
+    let dir = temp_dir("dropped");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+    let dogs = dogs();
+    save_dogs_async(path, &dogs[..1]).await.unwrap();
+    // Each save is dropped at a later point than the one before.
+    for micros in 0..50 {
+        let limit = Duration::from_micros(micros * 20);
+        let _ = timeout(limit, save_dogs_async(path, &dogs)).await;
+        let loaded = get_dogs_async(path).await.unwrap();
+        assert!(loaded == dogs || loaded == dogs[..1]);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn slow_operations_time_out() {
+    let slow = async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        get_dogs_async("tests/fixtures/dogs.json").await
+    };
+    let limit = Duration::from_millis(10);
+    match timeout(limit, slow).await {
+        Err(e @ GetDogsError::TimedOut(_)) => {
+            assert_eq!(e.exit_code(), 20);
+            assert_eq!(e.to_string(), "timed out after 10ms");
+        }
+        other => panic!("expected TimedOut, got {:?}", other),
+    }
+
+    let fast = get_dogs_async("tests/fixtures/dogs.json");
+    assert_eq!(
+        timeout(Duration::from_secs(10), fast).await.unwrap(),
+        dogs()
+    );
+}
+
+#[tokio::test]
+async fn operations_can_be_cancelled_from_another_task() {
+    let token = CancelToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        canceller.cancel();
+    });
+    let slow = async {
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        get_dogs_async("tests/fixtures/dogs.json").await
+    };
+    assert!(matches!(
+        cancellable(&token, slow).await,
+        Err(GetDogsError::Cancelled)
+    ));
+
+    // A token that is already cancelled stops operations immediately.
+    assert!(token.is_cancelled());
+    let fast = get_dogs_async("tests/fixtures/dogs.json");
+    assert!(matches!(
+        cancellable(&token, fast).await,
+        Err(GetDogsError::Cancelled)
+    ));
+}
+
+#[tokio::test]
+async fn cancelled_saves_leave_the_file_alone() {
+    let dir = temp_dir("cancelled-save");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+    save_dogs_async(path, &dogs()).await.unwrap();
+
+    let token = CancelToken::new();
+    token.cancel();
+    let result = cancellable(&token, save_dogs_async(path, &dogs()[..1])).await;
+    assert!(matches!(result, Err(GetDogsError::Cancelled)));
+    assert_eq!(get_dogs_async(path).await.unwrap(), dogs());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,114 @@
+#![cfg(any(feature = "msgpack", feature = "cbor"))]
+
+use rust_error_handling::{get_dogs, get_dogs3, Dog, FileFormat, GetDogsError, MyResult};
+use std::fs;
+
+mod common;
+use common::temp_dir;
+
+fn extended_dogs() -> Vec<Dog> {
+    get_dogs3("tests/fixtures/extended.json").unwrap()
+}
+
+// Checks a loader against a saver and the ways reading can fail.
+// The bad bytes must not be valid in the format.
+fn check_format(
+    name: &str,
+    save: fn(&str, &[Dog]) -> MyResult<()>,
+    load: fn(&str) -> MyResult<Vec<Dog>>,
+    bad: &[u8],
+) -> (GetDogsError, GetDogsError) {
+    let dir = temp_dir(name);
+    let path = dir.join(format!("dogs.{}", name));
+    let path = path.to_str().unwrap();
+    save(path, &extended_dogs()).unwrap();
+    assert_eq!(load(path).unwrap(), extended_dogs());
+    assert_eq!(get_dogs(path, None).unwrap(), extended_dogs());
+    // Binary files are smaller than the JSON they replace.
+    assert!(
+        fs::metadata(path).unwrap().len()
+            < fs::metadata("tests/fixtures/extended.json").unwrap().len()
+    );
+
+    assert!(matches!(
+        load("tests/fixtures/missing.bin"),
+        Err(GetDogsError::NotFound(_))
+    ));
+    fs::write(path, []).unwrap();
+    assert!(matches!(load(path), Err(GetDogsError::Empty)));
+    fs::write(path, bad).unwrap();
+    let corrupt = load(path).unwrap_err();
+    // Text isn't a list of dogs in either format.
+    fs::write(path, b"[{\"name\": \"Comet\"}]").unwrap();
+    let not_dogs = load(path).unwrap_err();
+    fs::remove_dir_all(dir).unwrap();
+    (corrupt, not_dogs)
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn msgpack_round_trips_and_reports_decode_errors() {
+    use rust_error_handling::{get_dogs_msgpack, save_dogs_msgpack};
+
+    // 0xC1 is never used in MessagePack.
+    let (corrupt, not_dogs) = check_format(
+        "msgpack",
+        save_dogs_msgpack,
+        get_dogs_msgpack,
+        &[0x91, 0xC1],
+    );
+    for e in [corrupt, not_dogs] {
+        match e {
+            GetDogsError::BadMessagePack(_) => {
+                assert_eq!(e.exit_code(), 26);
+                assert_eq!(e.code(), "E026");
+                assert!(e.to_string().starts_with("bad MessagePack: "));
+            }
+            other => panic!("expected BadMessagePack, got {:?}", other),
+        }
+    }
+    assert_eq!(
+        FileFormat::from_path("dogs.mpk"),
+        Some(FileFormat::MessagePack)
+    );
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor_round_trips_and_reports_decode_errors() {
+    use rust_error_handling::{get_dogs_cbor, save_dogs_cbor};
+
+    // 0x1C is a reserved additional information value.
+    let (corrupt, not_dogs) = check_format("cbor", save_dogs_cbor, get_dogs_cbor, &[0x81, 0x1C]);
+    for e in [corrupt, not_dogs] {
+        match e {
+            GetDogsError::BadCbor(_) => {
+                assert_eq!(e.exit_code(), 28);
+                assert_eq!(e.code(), "E028");
+                assert!(e.to_string().starts_with("bad CBOR: "));
+            }
+            other => panic!("expected BadCbor, got {:?}", other),
+        }
+    }
+    assert_eq!(FileFormat::from_path("dogs.cbor"), Some(FileFormat::Cbor));
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn binary_loaders_read_from_a_file_system() {
+    use rust_error_handling::{get_dogs_cbor_from, Fault, FaultyFileSystem};
+
+    let fs = FaultyFileSystem::new().with_fault("dogs.cbor", Fault::PermissionDenied);
+    assert!(matches!(
+        get_dogs_cbor_from(&fs, "dogs.cbor"),
+        Err(GetDogsError::PermissionDenied(_))
+    ));
+
+    // A CBOR array holding one map with only a name.
+    let bytes = vec![0x81, 0xA1, 0x64, b'n', b'a', b'm', b'e', 0x61, b'C'];
+    let fs = FaultyFileSystem::new().with_file("dogs.cbor", bytes);
+    assert!(matches!(
+        get_dogs_cbor_from(&fs, "dogs.cbor"),
+        Err(GetDogsError::BadCbor(_))
+    ));
+}
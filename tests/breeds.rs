@@ -0,0 +1,162 @@
+use rust_error_handling::{
+    canonicalize_breeds, get_dogs3, BreedMode, BreedRegistry, ErrorReport, FaultyFileSystem,
+    GetDogsError, UnknownBreed,
+};
+use std::process::Command;
+
+fn breeds(mode: BreedMode, registry: &BreedRegistry) -> Result<Vec<String>, GetDogsError> {
+    let mut dogs = get_dogs3("tests/fixtures/breeds.json").unwrap();
+    canonicalize_breeds(&mut dogs, registry, mode)?;
+    Ok(dogs.iter().map(|d| d.breed().to_string()).collect())
+}
+
+#[test]
+fn aliases_are_canonicalized_ignoring_case_hyphens_and_spaces() {
+    let registry = BreedRegistry::bundled();
+    for alias in ["GSP", "german shorthair", "German-Shorthaired  Pointer"] {
+        assert_eq!(
+            registry.canonicalize(alias),
+            Some("German Shorthaired Pointer")
+        );
+    }
+    assert_eq!(registry.canonicalize("Pointr"), None);
+
+    // Unknown breeds are kept in lenient mode.
+    assert_eq!(
+        breeds(BreedMode::Lenient, &registry).unwrap(),
+        vec![
+            "Whippet",
+            "German Shorthaired Pointer",
+            "German Shorthaired Pointer",
+            "Grayhound",
+            "Space Dog",
+        ]
+    );
+}
+
+#[test]
+fn strict_mode_reports_every_unknown_breed_with_suggestions() {
+    let err = breeds(BreedMode::Strict, &BreedRegistry::bundled()).unwrap_err();
+    match &err {
+        GetDogsError::UnknownBreeds(unknown) => assert_eq!(
+            unknown,
+            &vec![
+                UnknownBreed {
+                    index: 3,
+                    breed: "Grayhound".to_string(),
+                    suggestion: Some("Greyhound".to_string()),
+                },
+                UnknownBreed {
+                    index: 4,
+                    breed: "Space Dog".to_string(),
+                    suggestion: None,
+                },
+            ]
+        ),
+        other => panic!("expected UnknownBreeds, got {:?}", other),
+    }
+    assert_eq!(err.exit_code(), 23);
+    assert_eq!(err.http_status(), 422);
+    let report = ErrorReport::new(&err, None);
+    assert_eq!(report.code, "E023");
+    assert_eq!(report.violations.len(), 2);
+    assert_eq!(report.violations[1].field, "breed");
+}
+
+#[test]
+fn suggestions_use_edit_distance() {
+    let registry = BreedRegistry::bundled();
+    assert_eq!(registry.suggest("Whipet"), Some("Whippet"));
+    assert_eq!(registry.suggest("Grayhound"), Some("Greyhound"));
+    assert_eq!(
+        registry.suggest("labrador retreiver"),
+        Some("Labrador Retriever")
+    );
+    assert_eq!(registry.suggest("Space Dog"), None);
+    assert_eq!(registry.suggest("Pointr"), None);
+
+    let unknown = UnknownBreed {
+        index: 0,
+        breed: "Whipet".to_string(),
+        suggestion: Some("Whippet".to_string()),
+    };
+    assert_eq!(
+        unknown.to_string(),
+        "dog 0 has unknown breed \"Whipet\" (did you mean \"Whippet\"?)"
+    );
+}
+
+#[test]
+fn overrides_add_breeds_and_aliases() {
+    let registry = BreedRegistry::bundled()
+        .with_overrides("tests/fixtures/breeds.txt")
+        .unwrap();
+    assert_eq!(registry.canonicalize("astro dog"), Some("Space Dog"));
+    assert_eq!(registry.suggest("Pointr"), Some("Pointer"));
+    assert_eq!(
+        breeds(BreedMode::Strict, &registry)
+            .unwrap_err()
+            .to_string(),
+        "unknown breeds:\n  dog 3 has unknown breed \"Grayhound\" (did you mean \"Greyhound\"?)"
+    );
+
+    // A name in an override replaces the bundled meaning.
+    let fs = FaultyFileSystem::new().with_file("breeds.txt", b"Pointer: GSP".to_vec());
+    let registry = BreedRegistry::bundled()
+        .with_overrides_from(&fs, "breeds.txt")
+        .unwrap();
+    assert_eq!(registry.canonicalize("gsp"), Some("Pointer"));
+}
+
+#[test]
+fn bad_override_files_are_errors() {
+    let fs = FaultyFileSystem::new().with_file("breeds.txt", b"# ok\nBoxer\n: Pup\n".to_vec());
+    match BreedRegistry::bundled().with_overrides_from(&fs, "breeds.txt") {
+        Err(e @ GetDogsError::BadRegistry { line: 3, .. }) => assert_eq!(e.exit_code(), 24),
+        other => panic!("expected BadRegistry, got {:?}", other.map(|_| ())),
+    }
+    assert!(matches!(
+        BreedRegistry::bundled().with_overrides("tests/fixtures/missing.txt"),
+        Err(GetDogsError::NotFound(_))
+    ));
+}
+
+#[test]
+fn the_tool_canonicalizes_breeds() {
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_rust-error-handling"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let output = run(&["-f", "tests/fixtures/breeds.json", "list"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Oscar (German Shorthaired Pointer)"));
+
+    let output = run(&[
+        "-f",
+        "tests/fixtures/breeds.json",
+        "--strict-breeds",
+        "list",
+    ]);
+    assert_eq!(output.status.code(), Some(23));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("did you mean"));
+
+    let output = run(&[
+        "-f",
+        "tests/fixtures/breeds.json",
+        "--breeds",
+        "tests/fixtures/breeds.txt",
+        "--strict-breeds",
+        "stats",
+    ]);
+    assert_eq!(output.status.code(), Some(23));
+    let output = run(&[
+        "-f",
+        "tests/fixtures/missing.json",
+        "--breeds",
+        "tests/fixtures/missing.txt",
+        "list",
+    ]);
+    assert_eq!(output.status.code(), Some(16));
+}
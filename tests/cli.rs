@@ -0,0 +1,284 @@
+use std::fs;
+use std::process::{Command, Output};
+
+mod common;
+use common::temp_dir;
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rust-error-handling"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn list_formats() {
+    let output = run(&["--file", "tests/fixtures/dogs.json", "list"]);
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Comet (Whippet)\nOscar (German Shorthaired Pointer)\n"
+    );
+
+    let output = run(&["-f", "tests/fixtures/dogs.json", "list", "--format=table"]);
+    assert!(stdout(&output).starts_with("NAME   BREED\nComet  Whippet\n"));
+
+    let output = run(&["-f", "tests/fixtures/dogs.json", "--format", "json", "list"]);
+    let dogs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(dogs[1]["breed"], "German Shorthaired Pointer");
+}
+
+#[test]
+fn each_error_has_its_own_exit_code() {
+    let code = |args: &[&str]| run(args).status.code().unwrap();
+    assert_eq!(code(&["bogus"]), 2);
+    assert_eq!(code(&["list", "extra"]), 2);
+    assert_eq!(code(&["--format", "xml", "list"]), 2);
+    assert_eq!(code(&["-f", "tests/fixtures/missing.json", "list"]), 16);
+    assert_eq!(code(&["-f", "tests/fixtures/empty.json", "list"]), 19);
+    assert_eq!(code(&["-f", "tests/fixtures/malformed.json", "list"]), 4);
+    assert_eq!(code(&["-f", "tests/fixtures/invalid.json", "list"]), 5);
+    assert_eq!(
+        code(&["-f", "tests/fixtures/dogs.json", "remove", "Zed"]),
+        1
+    );
+}
+
+#[test]
+fn validate_reports_every_bad_dog() {
+    let output = run(&["-f", "tests/fixtures/mixed.json", "validate"]);
+    assert_eq!(output.status.code(), Some(4));
+    let text = stdout(&output);
+    assert!(text.contains("dog 1: bad JSON: missing field `breed`"));
+    assert!(text.contains("dog 2: invalid dogs:"));
+    assert!(text.ends_with("2 valid, 2 invalid\n"));
+}
+
+#[test]
+fn duplicates_are_checked_on_load() {
+    let file = "tests/fixtures/duplicates.json";
+    let output = run(&["-f", file, "validate"]);
+    assert_eq!(output.status.code(), Some(22));
+    let text = stdout(&output);
+    assert!(text.contains("duplicate dogs by name:\n  dogs 0 and 2 are both \"Comet\""));
+    assert!(text.ends_with("3 valid, 2 invalid\n"));
+
+    let output = run(&["-f", file, "--unique", "microchip", "validate"]);
+    assert_eq!(output.status.code(), Some(22));
+    assert!(stdout(&output).ends_with("4 valid, 1 invalid\n"));
+
+    // Other commands only check when given a key.
+    assert!(run(&["-f", file, "list"]).status.success());
+    assert_eq!(
+        run(&["-f", file, "--unique", "name+breed", "list"])
+            .status
+            .code(),
+        Some(22)
+    );
+    assert_eq!(
+        run(&["-f", file, "--unique", "tail", "list"]).status.code(),
+        Some(2)
+    );
+}
+
+#[test]
+fn validate_checks_breeds() {
+    let output = run(&[
+        "-f",
+        "tests/fixtures/breeds.json",
+        "--strict-breeds",
+        "validate",
+    ]);
+    assert_eq!(output.status.code(), Some(23));
+    assert!(stdout(&output).contains("unknown breeds:"));
+
+    // Dogs are numbered by their position in the file
+    // even when earlier dogs failed to load.
+    let output = run(&[
+        "-f",
+        "tests/fixtures/misspelled_breed.json",
+        "--strict-breeds",
+        "--format=json",
+        "validate",
+    ]);
+    assert_eq!(output.status.code(), Some(4));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["valid"], 0);
+    assert_eq!(report["errors"][1]["index"], 1);
+    assert_eq!(
+        report["errors"][1]["message"],
+        "dog 1 has unknown breed \"Whipet\" (did you mean \"Whippet\"?)"
+    );
+
+    // Without --strict-breeds, unknown breeds are allowed.
+    let output = run(&["-f", "tests/fixtures/misspelled_breed.json", "validate"]);
+    assert!(stdout(&output).ends_with("1 valid, 1 invalid\n"));
+}
+
+#[test]
+fn add_remove_and_convert() {
+    let dir = temp_dir("add-remove");
+    let file = dir.join("dogs.json");
+    let file = file.to_str().unwrap();
+
+    // Adding to a missing file creates it.
+    assert!(run(&["-f", file, "add", "Comet", "Whippet"])
+        .status
+        .success());
+    assert!(run(&["-f", file, "add", "Rex", "Boxer"]).status.success());
+    assert_eq!(
+        run(&["-f", file, "add", "", "Boxer"]).status.code(),
+        Some(5)
+    );
+    assert_eq!(
+        run(&["-f", file, "add", "Rex", "Pug"]).status.code(),
+        Some(22)
+    );
+    assert!(run(&["-f", file, "remove", "Rex"]).status.success());
+
+    let copy = dir.join("copy.json");
+    let copy = copy.to_str().unwrap();
+    assert!(run(&["-f", file, "convert", copy]).status.success());
+    let output = run(&["-f", copy, "list"]);
+    assert_eq!(stdout(&output), "Comet (Whippet)\n");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn editing_keeps_the_file_format() {
+    let dir = temp_dir("edit-formats");
+    let file = dir.join("dogs.csv");
+    fs::copy("tests/fixtures/dogs.csv", &file).unwrap();
+    let file = file.to_str().unwrap();
+
+    assert!(run(&["-f", file, "add", "Rex", "Boxer"]).status.success());
+    assert!(run(&["-f", file, "remove", "Oscar"]).status.success());
+    assert_eq!(
+        fs::read_to_string(file).unwrap(),
+        "name,breed\nComet,Whippet\nRex,Boxer\n"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn add_only_checks_the_new_dog_for_duplicates() {
+    let dir = temp_dir("add-duplicates");
+    let file = dir.join("dogs.json");
+    fs::copy("tests/fixtures/duplicates.json", &file).unwrap();
+    let file = file.to_str().unwrap();
+
+    assert!(run(&["-f", file, "add", "Rex", "Boxer"]).status.success());
+    let output = run(&["-f", file, "add", "Oscar", "Boxer"]);
+    assert_eq!(output.status.code(), Some(22));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("dogs 1 and 6 are both \"Oscar\""),
+        "{}",
+        stderr
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn dedupe_merges_duplicates() {
+    let dir = temp_dir("dedupe");
+    let file = dir.join("dogs.json");
+    fs::copy("tests/fixtures/duplicates.json", &file).unwrap();
+    let file = file.to_str().unwrap();
+
+    assert_eq!(
+        run(&["-f", file, "dedupe", "name", "oldest"]).status.code(),
+        Some(2)
+    );
+    let output = run(&["-f", file, "dedupe", "name+breed", "merge"]);
+    assert_eq!(stdout(&output), "removed 1 duplicate dogs\n");
+    let output = run(&["-f", file, "--format", "json", "list"]);
+    let dogs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(dogs[0]["sex"], "female");
+    assert_eq!(dogs[0]["weight"], 12.5);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn csv_converts_to_json() {
+    let output = run(&["-f", "tests/fixtures/dogs.csv", "--format", "json", "list"]);
+    let dogs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(dogs[0]["name"], "Comet");
+
+    let code = |args: &[&str]| run(args).status.code().unwrap();
+    assert_eq!(code(&["-f", "tests/fixtures/malformed.yaml", "list"]), 9);
+    assert_eq!(code(&["-f", "tests/fixtures/malformed.toml", "stats"]), 10);
+    assert_eq!(
+        code(&["-f", "tests/fixtures/malformed.csv", "validate"]),
+        11
+    );
+    assert_eq!(
+        code(&[
+            "-f",
+            "tests/fixtures/dogs.yaml",
+            "--input-format=json",
+            "list"
+        ]),
+        4
+    );
+}
+
+#[test]
+fn json_errors_are_written_to_stderr() {
+    let output = run(&["--json-errors", "-f", "tests/fixtures/missing.json", "list"]);
+    assert_eq!(output.status.code(), Some(16));
+    let report: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(report["kind"], "NotFound");
+    assert_eq!(report["code"], "E016");
+    assert_eq!(report["path"], "tests/fixtures/missing.json");
+
+    let output = run(&[
+        "--json-errors",
+        "-f",
+        "tests/fixtures/dogs.json",
+        "remove",
+        "Zed",
+    ]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(report["code"], "E001");
+}
+
+#[test]
+fn ndjson_errors_have_file_positions() {
+    let output = run(&["-f", "tests/fixtures/malformed.ndjson", "list"]);
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("dog 1 at line 3 column 16: bad JSON: "));
+
+    let output = run(&[
+        "--json-errors",
+        "-f",
+        "tests/fixtures/malformed.ndjson",
+        "list",
+    ]);
+    let report: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(report["kind"], "BadJson");
+    assert_eq!(report["line"], 3);
+    assert_eq!(report["column"], 16);
+}
+
+#[test]
+fn version_errors_have_exit_codes() {
+    let code = |args: &[&str]| run(args).status.code().unwrap();
+    assert_eq!(
+        code(&["-f", "tests/fixtures/future_version.json", "list"]),
+        13
+    );
+    assert_eq!(
+        code(&["-f", "tests/fixtures/v1_object.json", "validate"]),
+        14
+    );
+}
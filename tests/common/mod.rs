@@ -0,0 +1,30 @@
+// Helpers shared by the integration tests.
+// Each test file is its own crate and uses only some of these.
+#![allow(dead_code)]
+
+use rust_error_handling::Dog;
+use std::fs;
+use std::path::PathBuf;
+
+// Each test gets its own empty directory so they can run in parallel.
+// The name of the test file is included because
+// the test files also run in parallel and may reuse names.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "rust-error-handling-{}-{}-{}",
+        env!("CARGO_CRATE_NAME"),
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// The dogs in tests/fixtures/dogs.json.
+pub fn dogs() -> Vec<Dog> {
+    vec![
+        Dog::new("Comet", "Whippet"),
+        Dog::new("Oscar", "German Shorthaired Pointer"),
+    ]
+}
@@ -0,0 +1,62 @@
+use rust_error_handling::{get_dogs3, Context, GetDogsError, MyResult};
+use std::error::Error;
+
+fn load_roster() -> MyResult<()> {
+    get_dogs3("tests/fixtures/missing.json")
+        .context("reading tests/fixtures/missing.json")
+        .with_context(|| format!("loading kennel roster {}", 7))?;
+    Ok(())
+}
+
+#[test]
+fn frames_form_a_single_stack() {
+    match load_roster() {
+        Err(GetDogsError::WithContext { frames, source }) => {
+            assert_eq!(
+                frames,
+                vec![
+                    "reading tests/fixtures/missing.json",
+                    "loading kennel roster 7"
+                ]
+            );
+            assert!(matches!(*source, GetDogsError::NotFound(_)));
+        }
+        other => panic!("expected WithContext, got {:?}", other),
+    }
+}
+
+#[test]
+fn display_shows_the_whole_chain() {
+    let err = load_roster().unwrap_err();
+    let message = err.to_string();
+    assert!(message.starts_with(
+        "loading kennel roster 7: reading tests/fixtures/missing.json: file not found: "
+    ));
+    let alternate = format!("{:#}", err);
+    assert!(alternate.starts_with(
+        "loading kennel roster 7\ncaused by: reading tests/fixtures/missing.json\ncaused by: file not found: "
+    ));
+}
+
+#[test]
+fn source_chain_reaches_the_io_error() {
+    let err = load_roster().unwrap_err();
+    let inner = err.source().unwrap();
+    assert!(inner.to_string().starts_with("file not found: "));
+    assert!(inner.source().unwrap().is::<std::io::Error>());
+}
+
+#[test]
+fn root_and_exit_code_look_through_context() {
+    let err = load_roster().unwrap_err();
+    assert!(matches!(err.root(), GetDogsError::NotFound(_)));
+    assert_eq!(err.exit_code(), 16);
+}
+
+#[test]
+fn ok_results_are_unchanged() {
+    let dogs = get_dogs3("tests/fixtures/dogs.json")
+        .with_context(|| -> String { panic!("context should not be built") })
+        .unwrap();
+    assert_eq!(dogs.len(), 2);
+}
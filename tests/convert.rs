@@ -0,0 +1,172 @@
+use rust_error_handling::{
+    convert_dogs, get_dogs, get_dogs3, read_for_conversion, save_dogs_as, ConversionWarning,
+    FileFormat, GetDogsError,
+};
+use std::fs;
+use std::process::Command;
+
+mod common;
+use common::temp_dir;
+
+fn formats() -> Vec<&'static str> {
+    let mut formats = vec!["json", "ndjson", "yaml", "toml", "csv"];
+    if cfg!(feature = "msgpack") {
+        formats.push("msgpack");
+    }
+    if cfg!(feature = "cbor") {
+        formats.push("cbor");
+    }
+    formats
+}
+
+#[test]
+fn every_format_converts_to_every_other() {
+    let dir = temp_dir("round-trip");
+    let expected = get_dogs3("tests/fixtures/extended.json").unwrap();
+    for from in formats() {
+        let input = dir.join(format!("dogs.{}", from));
+        let input = input.to_str().unwrap();
+        save_dogs_as(input, &expected, None).unwrap();
+        for to in formats() {
+            let output = dir.join(format!("from-{}.{}", from, to));
+            let output = output.to_str().unwrap();
+            assert!(convert_dogs(input, None, output, None).unwrap().is_empty());
+            assert_eq!(
+                get_dogs(output, None).unwrap(),
+                expected,
+                "{} to {}",
+                from,
+                to
+            );
+        }
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fields_are_written_in_declaration_order() {
+    let dir = temp_dir("order");
+    let dogs = get_dogs3("tests/fixtures/extended.json").unwrap();
+
+    let csv = dir.join("dogs.csv");
+    save_dogs_as(csv.to_str().unwrap(), &dogs, None).unwrap();
+    assert_eq!(
+        fs::read_to_string(&csv).unwrap(),
+        "name,breed,birth_date,sex,weight,color,microchip,owner,notes\n\
+         Comet,Whippet,2016-02-29,female,12.5,brindle,985112345678901,Mark,Fast.\n\
+         Oscar,German Shorthaired Pointer,,,,,,,\n"
+    );
+
+    // The format can be given when the extension doesn't say.
+    let ndjson = dir.join("dogs.txt");
+    let ndjson = ndjson.to_str().unwrap();
+    save_dogs_as(ndjson, &dogs[1..], Some(FileFormat::Ndjson)).unwrap();
+    assert_eq!(
+        fs::read_to_string(ndjson).unwrap(),
+        "{\"name\":\"Oscar\",\"breed\":\"German Shorthaired Pointer\"}\n"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn dropped_fields_are_warnings() {
+    let conversion = read_for_conversion("tests/fixtures/extra_fields.json", None).unwrap();
+    assert_eq!(conversion.dogs.len(), 2);
+    assert_eq!(
+        conversion.warnings,
+        vec![
+            ConversionWarning {
+                field: "tags".to_string(),
+                dogs: vec![0, 1],
+                nested: true,
+            },
+            ConversionWarning {
+                field: "vet".to_string(),
+                dogs: vec![0],
+                nested: true,
+            },
+            ConversionWarning {
+                field: "nickname".to_string(),
+                dogs: vec![1],
+                nested: false,
+            },
+        ]
+    );
+    assert_eq!(
+        conversion.warnings[0].to_string(),
+        "nested field \"tags\" is not a dog field and was dropped from dogs 0, 1"
+    );
+
+    // Extra CSV columns are reported for the rows that have a value.
+    let dir = temp_dir("csv-columns");
+    let csv = dir.join("dogs.csv");
+    fs::write(
+        &csv,
+        "name,breed,nickname\nComet,Whippet,\nOscar,Boxer,Oz\n",
+    )
+    .unwrap();
+    let warnings = read_for_conversion(csv.to_str().unwrap(), None)
+        .unwrap()
+        .warnings;
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].dogs, vec![1]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn conversion_errors_are_get_dogs_errors() {
+    let dir = temp_dir("errors");
+    let output = dir.join("out.json");
+    let output = output.to_str().unwrap();
+    assert!(matches!(
+        convert_dogs("tests/fixtures/malformed.yaml", None, output, None),
+        Err(GetDogsError::BadYaml(_))
+    ));
+    assert!(matches!(
+        convert_dogs("tests/fixtures/missing.json", None, output, None),
+        Err(GetDogsError::NotFound(_))
+    ));
+    assert!(matches!(
+        convert_dogs(
+            "tests/fixtures/dogs.json",
+            None,
+            "/no/such/dir/dogs.csv",
+            None
+        ),
+        Err(GetDogsError::CannotWrite(_))
+    ));
+    assert!(!std::path::Path::new(output).exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn ndjson_is_chosen_by_extension() {
+    assert_eq!(
+        get_dogs("tests/fixtures/dogs.ndjson", None).unwrap(),
+        get_dogs3("tests/fixtures/dogs.json").unwrap()
+    );
+    assert_eq!(
+        FileFormat::from_path("dogs.jsonl"),
+        Some(FileFormat::Ndjson)
+    );
+}
+
+#[test]
+fn the_tool_prints_warnings_to_stderr() {
+    let dir = temp_dir("cli");
+    let output = dir.join("dogs.out");
+    let output = output.to_str().unwrap();
+    let result = Command::new(env!("CARGO_BIN_EXE_rust-error-handling"))
+        .args(["-f", "tests/fixtures/extra_fields.json", "convert", output])
+        .args(["--output-format", "csv"])
+        .output()
+        .unwrap();
+    assert!(result.status.success());
+    let stderr = String::from_utf8_lossy(&result.stderr);
+    assert_eq!(stderr.lines().count(), 3);
+    assert!(stderr.starts_with("warning: nested field \"tags\""));
+    assert!(fs::read_to_string(output)
+        .unwrap()
+        .starts_with("name,breed,birth_date,weight\n"));
+    fs::remove_dir_all(dir).unwrap();
+}
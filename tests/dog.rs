@@ -0,0 +1,111 @@
+use rust_error_handling::{
+    get_dogs, get_dogs3, load_dogs, save_dogs, stream_dogs, Date, Dog, GetDogsError, Mode, Sex,
+};
+use std::fs;
+
+mod common;
+use common::temp_dir;
+
+fn comet() -> Dog {
+    Dog::new("Comet", "Whippet")
+        .with_birth_date(Date::new(2016, 2, 29).unwrap())
+        .with_sex(Sex::Female)
+        .with_weight(12.5)
+        .with_color("brindle")
+        .with_microchip("985112345678901")
+        .with_owner("Mark")
+        .with_notes("Fast.")
+}
+
+#[test]
+fn optional_fields_load_and_default() {
+    let dogs = get_dogs3("tests/fixtures/extended.json").unwrap();
+    assert_eq!(dogs[0], comet());
+    assert_eq!(dogs[0].birth_date().unwrap().to_string(), "2016-02-29");
+    assert_eq!(dogs[1], Dog::new("Oscar", "German Shorthaired Pointer"));
+    assert_eq!(dogs[1].weight(), None);
+}
+
+#[test]
+fn optional_fields_load_from_csv() {
+    let dogs = get_dogs("tests/fixtures/extended.csv", None).unwrap();
+    assert_eq!(dogs[0].sex(), Some(Sex::Female));
+    assert_eq!(dogs[0].weight(), Some(12.5));
+    assert_eq!(dogs[1].birth_date(), None);
+}
+
+#[test]
+fn bad_values_are_reported_per_field() {
+    match get_dogs3("tests/fixtures/bad_date.json") {
+        Err(GetDogsError::BadField {
+            index,
+            field,
+            source,
+        }) => {
+            assert_eq!(index, 1);
+            assert_eq!(field, "birth_date");
+            assert!(source
+                .to_string()
+                .starts_with("day 29 is not between 1 and 28"));
+            assert_eq!(source.line(), 9);
+        }
+        other => panic!("expected BadField, got {:?}", other),
+    }
+    match get_dogs3("tests/fixtures/negative_weight.json") {
+        Err(GetDogsError::BadField { index, field, .. }) => {
+            assert_eq!((index, field.as_str()), (0, "weight"));
+        }
+        other => panic!("expected BadField, got {:?}", other),
+    }
+}
+
+#[test]
+fn bad_fields_keep_their_index_when_loading_one_at_a_time() {
+    let loaded = load_dogs("tests/fixtures/bad_date.json", Mode::Lenient).unwrap();
+    assert_eq!(loaded.dogs.len(), 1);
+    assert!(matches!(
+        loaded.errors[0].error,
+        GetDogsError::BadField { index: 1, .. }
+    ));
+
+    let file = fs::File::open("tests/fixtures/bad_date.json").unwrap();
+    let results: Vec<_> = stream_dogs(file).collect();
+    match results[1] {
+        Err(GetDogsError::InStream {
+            index: Some(1),
+            ref source,
+            ..
+        }) => assert!(matches!(**source, GetDogsError::BadField { index: 1, .. })),
+        ref other => panic!("expected InStream, got {:?}", other),
+    }
+}
+
+#[test]
+fn missing_fields_are_still_bad_json() {
+    assert!(matches!(
+        get_dogs3("tests/fixtures/mixed.json"),
+        Err(GetDogsError::BadJson(_))
+    ));
+}
+
+#[test]
+fn optional_fields_round_trip() {
+    let dir = temp_dir("optional-fields");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+    let dogs = vec![comet(), Dog::new("Oscar", "German Shorthaired Pointer")];
+    save_dogs(path, &dogs).unwrap();
+    assert_eq!(get_dogs3(path).unwrap(), dogs);
+    // Fields that are None are left out of the file.
+    assert!(!fs::read_to_string(path).unwrap().contains("null"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn dates_are_checked() {
+    assert!("2020-02-29".parse::<Date>().is_ok());
+    assert!("1900-02-29".parse::<Date>().is_err());
+    assert!("2020-13-01".parse::<Date>().is_err());
+    assert!("2020-1-01".parse::<Date>().is_err());
+    assert!("20-01-01-01".parse::<Date>().is_err());
+}
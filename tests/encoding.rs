@@ -0,0 +1,75 @@
+use rust_error_handling::{get_dogs3_from, Dog, FaultyFileSystem, GetDogsError, MyResult};
+
+const JSON: &str = r#"[{"name": "Comet", "breed": "Whippet"}]"#;
+
+fn load(bytes: Vec<u8>) -> MyResult<Vec<Dog>> {
+    let fs = FaultyFileSystem::new().with_file("dogs.json", bytes);
+    get_dogs3_from(&fs, "dogs.json")
+}
+
+fn utf16(bom: &[u8], text: &str, big_endian: bool) -> Vec<u8> {
+    let mut bytes = bom.to_vec();
+    for unit in text.encode_utf16() {
+        if big_endian {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        } else {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+fn encoding_error(result: MyResult<Vec<Dog>>) -> (usize, &'static str) {
+    match result {
+        Err(GetDogsError::Encoding { offset, encoding }) => (offset, encoding),
+        other => panic!("expected Encoding, got {:?}", other),
+    }
+}
+
+#[test]
+fn utf16_and_byte_order_marks_are_transcoded() {
+    let expected = vec![Dog::new("Comet", "Whippet")];
+    let mut utf8_bom = vec![0xEF, 0xBB, 0xBF];
+    utf8_bom.extend_from_slice(JSON.as_bytes());
+    assert_eq!(load(utf8_bom).unwrap(), expected);
+    assert_eq!(load(utf16(&[0xFF, 0xFE], JSON, false)).unwrap(), expected);
+    assert_eq!(load(utf16(&[0xFE, 0xFF], JSON, true)).unwrap(), expected);
+    assert_eq!(load(utf16(&[], JSON, false)).unwrap(), expected);
+    assert_eq!(load(utf16(&[], JSON, true)).unwrap(), expected);
+}
+
+#[test]
+fn encoding_errors_have_byte_offsets() {
+    let mut bytes = JSON.as_bytes().to_vec();
+    bytes[12] = 0xC3;
+    assert_eq!(encoding_error(load(bytes)), (12, "UTF-8"));
+
+    // Offsets include the byte order mark.
+    let mut bytes = vec![0xEF, 0xBB, 0xBF];
+    bytes.extend_from_slice(b"[\xFF]");
+    assert_eq!(encoding_error(load(bytes)), (4, "UTF-8"));
+
+    // This is a high surrogate without the low surrogate that must follow it.
+    let mut bytes = utf16(&[0xFF, 0xFE], "[", false);
+    bytes.extend_from_slice(&[0x3D, 0xD8, b']', 0]);
+    assert_eq!(encoding_error(load(bytes)), (4, "UTF-16LE"));
+
+    let mut bytes = utf16(&[0xFE, 0xFF], JSON, true);
+    bytes.push(0);
+    let len = bytes.len();
+    assert_eq!(encoding_error(load(bytes)), (len - 1, "UTF-16BE"));
+}
+
+#[test]
+fn empty_files_are_empty() {
+    assert!(matches!(load(Vec::new()), Err(GetDogsError::Empty)));
+    assert!(matches!(
+        load(b" \n\t\n".to_vec()),
+        Err(GetDogsError::Empty)
+    ));
+    assert!(matches!(
+        load(vec![0xEF, 0xBB, 0xBF]),
+        Err(GetDogsError::Empty)
+    ));
+    assert_eq!(GetDogsError::Empty.to_string(), "the file is empty");
+}
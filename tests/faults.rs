@@ -0,0 +1,165 @@
+use rust_error_handling::{
+    get_dogs1_from, get_dogs2_from, get_dogs3_from, Dog, Fault, FaultyFileSystem, GetDogsError,
+};
+use std::collections::BTreeSet;
+use std::io::{self, ErrorKind};
+
+mod common;
+use common::dogs;
+
+const PATH: &str = "dogs.json";
+
+// Each case is a fixture with an optional fault.
+// Together they cover every way reading and parsing can go wrong.
+const CASES: &[(&str, Option<Fault>)] = &[
+    ("dogs.json", Some(Fault::NotFound)),
+    ("dogs.json", Some(Fault::PermissionDenied)),
+    ("dogs.json", Some(Fault::InvalidUtf8(20))),
+    ("dogs.json", Some(Fault::FailAfter(20, ErrorKind::TimedOut))),
+    ("dogs.json", Some(Fault::Truncated(20))),
+    ("dogs.json", Some(Fault::Interrupted)),
+    ("malformed.json", None),
+    ("empty.json", None),
+    ("bad_date.json", None),
+    ("invalid.json", None),
+    ("future_version.json", None),
+    ("v1_object.json", None),
+    ("envelope.json", None),
+];
+
+fn fs(fixture: &str, fault: Option<Fault>) -> FaultyFileSystem {
+    let contents = std::fs::read(format!("tests/fixtures/{}", fixture)).unwrap();
+    let fs = FaultyFileSystem::new().with_file(PATH, contents);
+    match fault {
+        Some(fault) => fs.with_fault(PATH, fault),
+        None => fs,
+    }
+}
+
+fn io_kind(err: &GetDogsError) -> ErrorKind {
+    match err {
+        GetDogsError::BadFile(e) => e.kind(),
+        other => panic!("expected BadFile, got {:?}", other),
+    }
+}
+
+#[test]
+fn interrupted_reads_are_retried() {
+    let fs = fs("dogs.json", Some(Fault::Interrupted));
+    assert_eq!(get_dogs1_from(&fs, PATH).unwrap(), dogs());
+    assert_eq!(get_dogs2_from(&fs, PATH).unwrap(), dogs());
+    assert_eq!(get_dogs3_from(&fs, PATH).unwrap(), dogs());
+}
+
+// get_dogs2 reports every failure to read as BadFile,
+// but get_dogs3 has variants for the common ones.
+#[test]
+fn io_faults_are_bad_file_or_more_specific() {
+    let cases = [
+        (Fault::NotFound, ErrorKind::NotFound),
+        (Fault::PermissionDenied, ErrorKind::PermissionDenied),
+        (Fault::InvalidUtf8(0), ErrorKind::InvalidData),
+        (
+            Fault::FailAfter(5, ErrorKind::WouldBlock),
+            ErrorKind::WouldBlock,
+        ),
+    ];
+    for &(fault, kind) in &cases {
+        let fs = fs("dogs.json", Some(fault));
+        let err = get_dogs1_from(&fs, PATH).unwrap_err();
+        assert_eq!(err.downcast_ref::<io::Error>().unwrap().kind(), kind);
+        assert_eq!(io_kind(&get_dogs2_from(&fs, PATH).unwrap_err()), kind);
+        let expected = match kind {
+            ErrorKind::NotFound => "NotFound",
+            ErrorKind::PermissionDenied => "PermissionDenied",
+            ErrorKind::InvalidData => "Encoding",
+            _ => "BadFile",
+        };
+        assert_eq!(get_dogs3_from(&fs, PATH).unwrap_err().kind(), expected);
+    }
+    // A path that was never added is missing too.
+    let fs = FaultyFileSystem::new();
+    assert!(matches!(
+        get_dogs3_from(&fs, PATH),
+        Err(GetDogsError::NotFound(_))
+    ));
+}
+
+#[test]
+fn truncated_files_are_bad_json() {
+    let fs = fs("dogs.json", Some(Fault::Truncated(20)));
+    let err = get_dogs1_from(&fs, PATH).unwrap_err();
+    assert!(err.downcast_ref::<serde_json::Error>().unwrap().is_eof());
+    match get_dogs3_from(&fs, PATH) {
+        Err(GetDogsError::BadJson(e)) => assert!(e.is_eof()),
+        other => panic!("expected BadJson, got {:?}", other),
+    }
+}
+
+// get_dogs1 has two kinds of errors for reading and parsing,
+// which callers must downcast, and versioned files add a third.
+#[test]
+fn get_dogs1_errors_are_io_json_or_version() {
+    for &(fixture, fault) in CASES {
+        if let Err(err) = get_dogs1_from(&fs(fixture, fault), PATH) {
+            let version = matches!(
+                err.downcast_ref::<GetDogsError>(),
+                Some(GetDogsError::UnsupportedVersion(_))
+                    | Some(GetDogsError::MigrationFailed { .. })
+            );
+            assert!(
+                err.is::<io::Error>() || err.is::<serde_json::Error>() || version,
+                "{} {:?}: {}",
+                fixture,
+                fault,
+                err
+            );
+        }
+    }
+}
+
+fn kinds(
+    load: impl Fn(&FaultyFileSystem) -> Result<Vec<Dog>, GetDogsError>,
+) -> BTreeSet<&'static str> {
+    CASES
+        .iter()
+        .filter_map(|&(fixture, fault)| load(&fs(fixture, fault)).err())
+        .map(|err| err.kind())
+        .collect()
+}
+
+// get_dogs2 parses the file without validating the dogs,
+// so everything beyond reading and parsing succeeds, is BadJson
+// or is a problem with the file's version.
+#[test]
+fn get_dogs2_errors_are_bad_file_bad_json_or_version() {
+    let kinds = kinds(|fs| get_dogs2_from(fs, PATH));
+    let expected = [
+        "BadFile",
+        "BadJson",
+        "UnsupportedVersion",
+        "MigrationFailed",
+    ];
+    assert_eq!(kinds, expected.iter().copied().collect());
+}
+
+// These are all the variants that loading can produce.
+// The others come from saving, from other formats, from stores
+// or from adding context.
+#[test]
+fn get_dogs3_produces_every_loading_variant() {
+    let kinds = kinds(|fs| get_dogs3_from(fs, PATH));
+    let expected = [
+        "BadFile",
+        "NotFound",
+        "PermissionDenied",
+        "Encoding",
+        "Empty",
+        "BadJson",
+        "BadField",
+        "Invalid",
+        "UnsupportedVersion",
+        "MigrationFailed",
+    ];
+    assert_eq!(kinds, expected.iter().copied().collect());
+}
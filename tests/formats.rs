@@ -0,0 +1,55 @@
+use rust_error_handling::{get_dogs, FileFormat, GetDogsError};
+
+mod common;
+use common::dogs;
+
+#[test]
+fn format_is_chosen_by_extension() {
+    for path in &[
+        "tests/fixtures/dogs.json",
+        "tests/fixtures/dogs.yaml",
+        "tests/fixtures/dogs.toml",
+        "tests/fixtures/dogs.csv",
+    ] {
+        assert_eq!(get_dogs(path, None).unwrap(), dogs(), "{}", path);
+    }
+}
+
+#[test]
+fn explicit_format_overrides_extension() {
+    // YAML is a superset of JSON, so a JSON file can be read as YAML.
+    let loaded = get_dogs("tests/fixtures/dogs.json", Some(FileFormat::Yaml)).unwrap();
+    assert_eq!(loaded, dogs());
+    assert!(matches!(
+        get_dogs("tests/fixtures/dogs.yaml", Some(FileFormat::Json)),
+        Err(GetDogsError::BadJson(_))
+    ));
+}
+
+#[test]
+fn each_format_has_its_own_error() {
+    assert!(matches!(
+        get_dogs("tests/fixtures/malformed.yaml", None),
+        Err(GetDogsError::BadYaml(_))
+    ));
+    assert!(matches!(
+        get_dogs("tests/fixtures/malformed.toml", None),
+        Err(GetDogsError::BadToml(_))
+    ));
+    assert!(matches!(
+        get_dogs("tests/fixtures/malformed.csv", None),
+        Err(GetDogsError::BadCsv(_))
+    ));
+    assert!(matches!(
+        get_dogs("tests/fixtures/missing.csv", None),
+        Err(GetDogsError::NotFound(_))
+    ));
+}
+
+#[test]
+fn format_names_parse() {
+    assert_eq!("YML".parse(), Ok(FileFormat::Yaml));
+    assert_eq!(FileFormat::from_path("dogs.csv"), Some(FileFormat::Csv));
+    assert_eq!(FileFormat::from_path("dogs"), None);
+    assert!("xml".parse::<FileFormat>().is_err());
+}
@@ -0,0 +1,92 @@
+use rust_error_handling::{
+    get_dogs1, get_dogs2, get_dogs3, load_dogs, render_json_error, GetDogsError, Mode, Problem,
+};
+
+mod common;
+use common::dogs;
+
+const DOGS: &str = "tests/fixtures/dogs.json";
+const MALFORMED: &str = "tests/fixtures/malformed.json";
+const INVALID: &str = "tests/fixtures/invalid.json";
+const MIXED: &str = "tests/fixtures/mixed.json";
+const MISSING: &str = "tests/fixtures/missing.json";
+
+#[test]
+fn all_loaders_read_valid_file() {
+    assert_eq!(get_dogs1(DOGS).unwrap(), dogs());
+    assert_eq!(get_dogs2(DOGS).unwrap(), dogs());
+    assert_eq!(get_dogs3(DOGS).unwrap(), dogs());
+}
+
+#[test]
+fn get_dogs1_errors_can_be_downcast() {
+    let err = get_dogs1(MISSING).unwrap_err();
+    assert!(err.downcast_ref::<std::io::Error>().is_some());
+    let err = get_dogs1(MALFORMED).unwrap_err();
+    assert!(err.downcast_ref::<serde_json::Error>().is_some());
+}
+
+#[test]
+fn missing_file_is_bad_file_or_not_found() {
+    assert!(matches!(get_dogs2(MISSING), Err(GetDogsError::BadFile(_))));
+    assert!(matches!(get_dogs3(MISSING), Err(GetDogsError::NotFound(_))));
+}
+
+#[test]
+fn malformed_file_is_bad_json() {
+    assert!(matches!(
+        get_dogs2(MALFORMED),
+        Err(GetDogsError::BadJson(_))
+    ));
+    assert!(matches!(
+        get_dogs3(MALFORMED),
+        Err(GetDogsError::BadJson(_))
+    ));
+}
+
+#[test]
+fn get_dogs3_reports_every_violation() {
+    match get_dogs3(INVALID) {
+        Err(GetDogsError::Invalid(violations)) => {
+            assert_eq!(violations.len(), 2);
+            assert_eq!(violations[0].index, 0);
+            assert_eq!(violations[0].field, "name");
+            assert_eq!(violations[0].problem, Problem::Empty);
+            assert_eq!(violations[1].index, 1);
+            assert_eq!(violations[1].problem, Problem::Untrimmed);
+        }
+        other => panic!("expected Invalid, got {:?}", other),
+    }
+}
+
+#[test]
+fn lenient_mode_keeps_good_dogs() {
+    let loaded = load_dogs(MIXED, Mode::Lenient).unwrap();
+    assert_eq!(loaded.dogs, dogs());
+    let indices: Vec<usize> = loaded.errors.iter().map(|e| e.index).collect();
+    assert_eq!(indices, vec![1, 2]);
+    assert!(matches!(loaded.errors[0].error, GetDogsError::BadJson(_)));
+    assert!(matches!(loaded.errors[1].error, GetDogsError::Invalid(_)));
+}
+
+#[test]
+fn strict_mode_fails_on_first_bad_dog() {
+    assert!(matches!(
+        load_dogs(MIXED, Mode::Strict),
+        Err(GetDogsError::BadJson(_))
+    ));
+    assert_eq!(load_dogs(DOGS, Mode::Strict).unwrap().dogs, dogs());
+}
+
+#[test]
+fn diagnostic_points_at_failure() {
+    let source = std::fs::read_to_string(MALFORMED).unwrap();
+    let err = match get_dogs3(MALFORMED) {
+        Err(GetDogsError::BadJson(e)) => e,
+        other => panic!("expected BadJson, got {:?}", other),
+    };
+    let rendered = render_json_error(MALFORMED, &source, &err);
+    assert!(rendered.contains(" --> tests/fixtures/malformed.json:4:9"));
+    assert!(rendered.contains("3 |         \"name\": \"Comet\"\n"));
+    assert!(rendered.ends_with("  |         ^\n"));
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "metrics")]
+
+use rust_error_handling::{get_dogs, get_dogs1, get_dogs3, stream_dogs, GetDogsError, Metrics};
+use std::process::Command;
+
+mod common;
+use common::temp_dir;
+
+#[test]
+fn counters_are_written_for_prometheus() {
+    let metrics = Metrics::new();
+    metrics.record("load", &Ok(()));
+    metrics.record::<()>("load", &Err(GetDogsError::Empty));
+    metrics.record::<()>("save", &Err(GetDogsError::Cancelled));
+    assert_eq!(metrics.total("load"), 2);
+    assert_eq!(metrics.successes("load"), 1);
+    assert_eq!(metrics.failures("load", "Empty"), 1);
+    assert_eq!(metrics.failures("load", "Cancelled"), 0);
+    assert_eq!(
+        metrics.to_prometheus(),
+        "\
+# HELP dogs_operations_total Loads and saves of dog files.
+# TYPE dogs_operations_total counter
+dogs_operations_total{operation=\"load\"} 2
+dogs_operations_total{operation=\"save\"} 1
+# HELP dogs_operation_successes_total Loads and saves of dog files that succeeded.
+# TYPE dogs_operation_successes_total counter
+dogs_operation_successes_total{operation=\"load\"} 1
+# HELP dogs_operation_failures_total Loads and saves of dog files that failed, by kind of error.
+# TYPE dogs_operation_failures_total counter
+dogs_operation_failures_total{operation=\"load\",kind=\"Empty\"} 1
+dogs_operation_failures_total{operation=\"save\",kind=\"Cancelled\"} 1
+"
+    );
+}
+
+// Other tests load files at the same time, so this only checks
+// counters that they can't change.
+#[test]
+fn loaders_count_themselves_by_variant() {
+    let global = Metrics::global();
+    let before = global.failures("load", "BadToml");
+    get_dogs("tests/fixtures/malformed.toml", None).unwrap_err();
+    get_dogs3("tests/fixtures/dogs.json").unwrap();
+    assert_eq!(global.failures("load", "BadToml"), before + 1);
+    assert!(global.successes("load") >= 1);
+}
+
+#[test]
+fn every_loader_is_counted() {
+    let global = Metrics::global();
+    let bad_files = global.failures("load", "BadFile");
+    let bad_syntax = global.failures("load", "BadSyntax");
+    get_dogs1("tests/fixtures/missing.json").unwrap_err();
+    let streamed: Vec<_> =
+        stream_dogs(&b"[{\"name\": \"Rex\", \"breed\": \"Boxer\"},]"[..]).collect();
+    assert!(streamed.last().unwrap().is_err());
+    assert_eq!(global.failures("load", "BadFile"), bad_files + 1);
+    assert_eq!(global.failures("load", "BadSyntax"), bad_syntax + 1);
+}
+
+#[test]
+fn the_tool_writes_metrics_when_it_exits() {
+    let dir = temp_dir("tool");
+    let path = dir.join("dogs.prom");
+    let result = Command::new(env!("CARGO_BIN_EXE_rust-error-handling"))
+        .args(["-f", "tests/fixtures/missing.json", "list", "--metrics"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(result.status.code(), Some(16));
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains("dogs_operations_total{operation=\"load\"} 1\n"));
+    assert!(
+        text.contains("dogs_operation_failures_total{operation=\"load\",kind=\"NotFound\"} 1\n")
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,148 @@
+use rust_error_handling::{
+    query_dogs, Date, Dog, ErrorReport, GetDogsError, Query, QueryError, Sex,
+};
+use std::process::Command;
+
+fn dogs() -> Vec<Dog> {
+    vec![
+        Dog::new("Comet", "Whippet")
+            .with_weight(12.5)
+            .with_sex(Sex::Female),
+        Dog::new("Oscar", "German Shorthaired Pointer")
+            .with_weight(27.0)
+            .with_birth_date(Date::new(2016, 3, 9).unwrap()),
+        Dog::new("Cleo", "Whippet").with_birth_date(Date::new(2019, 11, 2).unwrap()),
+        Dog::new("Maisey", "Treeing Walker Coonhound").with_weight(25.0),
+    ]
+}
+
+fn names(query: &str) -> Vec<String> {
+    query_dogs(dogs(), query)
+        .unwrap()
+        .iter()
+        .map(|d| d.name().to_string())
+        .collect()
+}
+
+fn error(query: &str) -> QueryError {
+    match Query::parse(query) {
+        Err(e) => e,
+        Ok(q) => panic!("expected an error, got {:?}", q),
+    }
+}
+
+#[test]
+fn conditions_filter_dogs() {
+    assert_eq!(names(""), vec!["Comet", "Oscar", "Cleo", "Maisey"]);
+    assert_eq!(
+        names(r#"breed = "Whippet" and name ~ "^C""#),
+        vec!["Comet", "Cleo"]
+    );
+    assert_eq!(names("weight >= 25"), vec!["Oscar", "Maisey"]);
+    assert_eq!(names("birth_date < 2018-01-01"), vec!["Oscar"]);
+    assert_eq!(
+        names("sex = female or name = Maisey"),
+        vec!["Comet", "Maisey"]
+    );
+    assert_eq!(
+        names("NOT (breed = Whippet OR weight > 26)"),
+        vec!["Maisey"]
+    );
+    // Dogs without a weight match neither comparison.
+    assert_eq!(
+        names("weight < 20 or weight >= 20"),
+        vec!["Comet", "Oscar", "Maisey"]
+    );
+    assert_eq!(names(r#"name = "Rex""#), Vec::<String>::new());
+}
+
+#[test]
+fn results_can_be_sorted_and_limited() {
+    assert_eq!(
+        names("sort by name"),
+        vec!["Cleo", "Comet", "Maisey", "Oscar"]
+    );
+    // Dogs without a weight sort last in both directions.
+    assert_eq!(
+        names("sort by weight desc"),
+        vec!["Oscar", "Maisey", "Comet", "Cleo"]
+    );
+    assert_eq!(
+        names("sort by breed desc, name asc limit 3"),
+        vec!["Cleo", "Comet", "Maisey"]
+    );
+    assert_eq!(names("breed = Whippet limit 1"), vec!["Comet"]);
+    assert_eq!(names("limit 0"), Vec::<String>::new());
+}
+
+#[test]
+fn parse_errors_have_positions() {
+    let cases = [
+        ("colour = red", 0, r#"unknown field "colour""#),
+        ("name", 4, "expected an operator"),
+        ("name = ", 7, "expected a value"),
+        (r#"name = "Comet"#, 7, "unterminated string"),
+        (
+            "weight > heavy",
+            9,
+            r#"weight must be compared with a number, not "heavy""#,
+        ),
+        ("name = Comet and", 16, "expected a field name"),
+        ("(name = Comet", 13, r#"expected ")""#),
+        ("name = Comet sort name", 18, r#"expected "by""#),
+        ("limit ten", 6, "expected a number after limit"),
+        ("name = Comet Cleo", 13, r#"unexpected "Cleo""#),
+        ("weight ~ 1", 9, "only text fields can be matched with ~"),
+        ("name ~ \"(\"", 7, "bad pattern: unclosed group"),
+        ("name # 1", 5, "unexpected character '#'"),
+    ];
+    for (query, position, message) in cases {
+        assert_eq!(
+            error(query),
+            QueryError {
+                position,
+                message: message.to_string()
+            },
+            "{}",
+            query
+        );
+    }
+    assert_eq!(
+        error("name").to_string(),
+        "expected an operator at position 4"
+    );
+}
+
+#[test]
+fn query_errors_convert_to_get_dogs_errors() {
+    let err = query_dogs(dogs(), "name ~").unwrap_err();
+    assert!(matches!(err, GetDogsError::BadQuery(_)));
+    assert_eq!(err.exit_code(), 25);
+    assert_eq!(err.http_status(), 400);
+    let report = ErrorReport::new(&err, None);
+    assert_eq!(report.code, "E025");
+    assert_eq!((report.line, report.column), (Some(1), Some(7)));
+
+    let err: GetDogsError = error("bogus").into();
+    assert_eq!(
+        err.to_string(),
+        r#"bad query: unknown field "bogus" at position 0"#
+    );
+}
+
+#[test]
+fn the_tool_runs_queries() {
+    let run = |query: &str| {
+        Command::new(env!("CARGO_BIN_EXE_rust-error-handling"))
+            .args(["-f", "tests/fixtures/duplicates.json", "query", query])
+            .output()
+            .unwrap()
+    };
+    let output = run("name = Comet and sex = male or breed ~ Box sort by name desc");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Ozzy (Boxer)\nComet (Whippet)\n"
+    );
+    assert_eq!(run("name =").status.code(), Some(25));
+}
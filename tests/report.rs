@@ -0,0 +1,62 @@
+use rust_error_handling::{get_dogs, get_dogs3, Context, ErrorReport};
+
+#[test]
+fn bad_json_report_has_position_and_sources() {
+    let path = "tests/fixtures/malformed.json";
+    let err = get_dogs3(path).context("loading roster").unwrap_err();
+    let report = ErrorReport::new(&err, Some(path));
+    assert_eq!(report.kind, "BadJson");
+    assert_eq!(report.code, "E004");
+    assert_eq!(report.path.as_deref(), Some(path));
+    assert_eq!((report.line, report.column), (Some(4), Some(9)));
+    assert_eq!(report.context, vec!["loading roster"]);
+    assert_eq!(
+        report.sources,
+        vec!["expected `,` or `}` at line 4 column 9"]
+    );
+}
+
+#[test]
+fn invalid_report_lists_violations() {
+    let err = get_dogs3("tests/fixtures/invalid.json").unwrap_err();
+    let report = ErrorReport::new(&err, None);
+    assert_eq!(report.code, "E005");
+    assert!(report.sources.is_empty());
+    assert_eq!(report.violations.len(), 2);
+    assert_eq!(report.violations[1].index, 1);
+    assert_eq!(report.violations[1].field, "name");
+}
+
+#[test]
+fn yaml_report_has_position() {
+    let err = get_dogs("tests/fixtures/malformed.yaml", None).unwrap_err();
+    let report = ErrorReport::new(&err, None);
+    assert_eq!(report.kind, "BadYaml");
+    assert_eq!(report.code, "E009");
+    assert!(report.line.is_some());
+}
+
+#[test]
+fn json_has_every_field() {
+    let err = get_dogs3("tests/fixtures/missing.json").unwrap_err();
+    let json: serde_json::Value =
+        serde_json::from_str(&ErrorReport::new(&err, None).to_json()).unwrap();
+    let mut keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            "code",
+            "column",
+            "context",
+            "kind",
+            "line",
+            "message",
+            "path",
+            "sources",
+            "violations"
+        ]
+    );
+    assert_eq!(json["code"], "E016");
+    assert!(json["path"].is_null());
+}
@@ -0,0 +1,140 @@
+use rust_error_handling::{
+    get_dogs3_from, Context, Fault, FaultyFileSystem, GetDogsError, RetryPolicy,
+};
+use std::io::{self, ErrorKind};
+use std::time::{Duration, Instant};
+
+const PATH: &str = "dogs.json";
+
+fn fs(fault: Option<Fault>) -> FaultyFileSystem {
+    let contents = std::fs::read("tests/fixtures/dogs.json").unwrap();
+    let fs = FaultyFileSystem::new().with_file(PATH, contents);
+    match fault {
+        Some(fault) => fs.with_fault(PATH, fault),
+        None => fs,
+    }
+}
+
+// The tests don't wait between attempts.
+fn policy(max_attempts: usize) -> RetryPolicy {
+    RetryPolicy::new()
+        .with_max_attempts(max_attempts)
+        .with_initial_delay(Duration::ZERO)
+}
+
+#[test]
+fn only_some_read_failures_are_transient() {
+    for kind in [
+        ErrorKind::Interrupted,
+        ErrorKind::WouldBlock,
+        ErrorKind::TimedOut,
+    ] {
+        assert!(GetDogsError::BadFile(io::Error::from(kind)).is_transient());
+    }
+    assert!(GetDogsError::TimedOut(Duration::from_secs(1)).is_transient());
+    assert!(!GetDogsError::BadFile(io::Error::from(ErrorKind::Other)).is_transient());
+    assert!(!GetDogsError::NotFound(io::Error::from(ErrorKind::NotFound)).is_transient());
+    assert!(!GetDogsError::Empty.is_transient());
+
+    let err: Result<(), _> = Err(GetDogsError::BadFile(io::Error::from(ErrorKind::TimedOut)));
+    assert!(err.context("loading dogs").unwrap_err().is_transient());
+    let err = GetDogsError::InStream {
+        index: Some(0),
+        line: 1,
+        column: 1,
+        source: Box::new(GetDogsError::TimedOut(Duration::from_secs(1))),
+    };
+    assert!(err.is_transient());
+}
+
+#[test]
+fn transient_failures_are_retried_until_the_load_succeeds() {
+    let flaky = fs(Some(Fault::FailAfter(20, ErrorKind::WouldBlock)));
+    let good = fs(None);
+    let mut attempts = 0;
+    let dogs = policy(3)
+        .run(|| {
+            attempts += 1;
+            let fs = if attempts < 3 { &flaky } else { &good };
+            get_dogs3_from(fs, PATH)
+        })
+        .unwrap();
+    assert_eq!(attempts, 3);
+    assert_eq!(dogs.len(), 2);
+}
+
+#[test]
+fn the_error_of_every_attempt_is_kept() {
+    let flaky = fs(Some(Fault::FailAfter(20, ErrorKind::TimedOut)));
+    let err = policy(3).run(|| get_dogs3_from(&flaky, PATH)).unwrap_err();
+    match &err {
+        GetDogsError::Retried { earlier, source } => {
+            assert_eq!(earlier.len(), 2);
+            assert!(earlier.iter().all(GetDogsError::is_transient));
+            assert!(matches!(**source, GetDogsError::BadFile(_)));
+        }
+        other => panic!("expected Retried, got {:?}", other),
+    }
+    // It is reported like the last error.
+    assert_eq!(err.exit_code(), 3);
+    assert_eq!(err.kind(), "BadFile");
+    assert!(!err.is_transient());
+    assert!(err
+        .to_string()
+        .starts_with("failed after 3 attempts: bad file: "));
+    let lines: Vec<String> = format!("{:#}", err).lines().map(String::from).collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[3].starts_with("  attempt 3: bad file: "));
+}
+
+#[test]
+fn other_failures_are_not_retried() {
+    let missing = FaultyFileSystem::new();
+    let mut attempts = 0;
+    let err = policy(5)
+        .run(|| {
+            attempts += 1;
+            get_dogs3_from(&missing, PATH)
+        })
+        .unwrap_err();
+    assert_eq!(attempts, 1);
+    assert!(matches!(err, GetDogsError::NotFound(_)));
+}
+
+#[test]
+fn delays_grow_exponentially_up_to_the_maximum() {
+    let policy = RetryPolicy::new()
+        .with_initial_delay(Duration::from_millis(100))
+        .with_multiplier(2.0)
+        .with_max_delay(Duration::from_millis(500));
+    let delays: Vec<u128> = (0..5).map(|i| policy.backoff(i).as_millis()).collect();
+    assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+    assert_eq!(policy.backoff(usize::MAX), Duration::from_millis(500));
+
+    // Delays too long for a Duration are the maximum.
+    let policy = RetryPolicy::new()
+        .with_initial_delay(Duration::from_secs(1))
+        .with_max_delay(Duration::MAX);
+    assert_eq!(policy.backoff(0), Duration::from_secs(1));
+    assert_eq!(policy.backoff(64), Duration::MAX);
+    assert_eq!(policy.backoff(usize::MAX), Duration::MAX);
+
+    // Delays never shrink, or become negative.
+    for multiplier in [-1.0, 0.5, f64::NAN] {
+        let policy = RetryPolicy::new().with_multiplier(multiplier);
+        assert_eq!(policy.backoff(3), Duration::from_millis(100));
+    }
+    let policy = RetryPolicy::new().with_jitter(f64::NAN);
+    assert_eq!(policy, RetryPolicy::new().with_jitter(0.0));
+
+    // Without jitter the whole delay is waited.
+    let flaky = fs(Some(Fault::FailAfter(0, ErrorKind::TimedOut)));
+    let start = Instant::now();
+    RetryPolicy::new()
+        .with_max_attempts(2)
+        .with_initial_delay(Duration::from_millis(30))
+        .with_jitter(0.0)
+        .run(|| get_dogs3_from(&flaky, PATH))
+        .unwrap_err();
+    assert!(start.elapsed() >= Duration::from_millis(30));
+}
@@ -0,0 +1,124 @@
+use rust_error_handling::{
+    get_dogs, get_dogs1, get_dogs2, get_dogs3, load_dogs, save_dogs, stream_dogs, FileFormat,
+    GetDogsError, Mode, MyResult,
+};
+use std::fs;
+
+mod common;
+use common::{dogs, temp_dir};
+
+#[test]
+fn saved_dogs_load_again() {
+    let dir = temp_dir("round-trip");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+
+    save_dogs(path, &dogs()).unwrap();
+    assert_eq!(get_dogs3(path).unwrap(), dogs());
+
+    // Saving again replaces the file and leaves no temporary files behind.
+    save_dogs(path, &dogs()[..1]).unwrap();
+    assert_eq!(get_dogs3(path).unwrap(), dogs()[..1]);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+// Saved files are versioned documents, which every loader must read.
+#[test]
+fn every_loader_reads_saved_files() {
+    let dir = temp_dir("every-loader");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+    save_dogs(path, &dogs()).unwrap();
+
+    assert_eq!(get_dogs1(path).unwrap(), dogs());
+    assert_eq!(get_dogs2(path).unwrap(), dogs());
+    assert_eq!(get_dogs3(path).unwrap(), dogs());
+    assert_eq!(get_dogs(path, None).unwrap(), dogs());
+    assert_eq!(get_dogs(path, Some(FileFormat::Json)).unwrap(), dogs());
+    for mode in [Mode::Strict, Mode::Lenient] {
+        let loaded = load_dogs(path, mode).unwrap();
+        assert_eq!(loaded.dogs, dogs());
+        assert!(loaded.errors.is_empty());
+    }
+    let streamed: MyResult<Vec<_>> = stream_dogs(fs::File::open(path).unwrap()).collect();
+    assert_eq!(streamed.unwrap(), dogs());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn concurrent_saves_of_one_file_dont_collide() {
+    let dir = temp_dir("concurrent");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+
+    std::thread::scope(|scope| {
+        let saves: Vec<_> = (0..8)
+            .map(|i| scope.spawn(move || save_dogs(path, &dogs()[..i % 2 + 1])))
+            .collect();
+        for save in saves {
+            save.join().unwrap().unwrap();
+        }
+    });
+    // Whichever save finished last wrote a whole file.
+    assert!(!get_dogs3(path).unwrap().is_empty());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn saved_file_matches_fixture_layout() {
+    let dir = temp_dir("layout");
+    let path = dir.join("dogs.json");
+    save_dogs(path.to_str().unwrap(), &dogs()).unwrap();
+    let saved = fs::read_to_string(&path).unwrap();
+    let fixture = fs::read_to_string("tests/fixtures/envelope.json").unwrap();
+    assert_eq!(saved.trim_end(), fixture.trim_end());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_directory_is_cannot_write() {
+    let dir = temp_dir("missing-dir");
+    let path = dir.join("no-such-dir").join("dogs.json");
+    let result = save_dogs(path.to_str().unwrap(), &dogs());
+    assert!(matches!(result, Err(GetDogsError::CannotWrite(_))));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+// Temporary files are named by process id and a counter,
+// so this blocks every name the saves in this process can use.
+#[test]
+fn existing_temp_files_are_left_alone() {
+    let dir = temp_dir("temp-taken");
+    let path = dir.join("dogs.json");
+    for count in 0..64 {
+        let name = format!(".dogs.json.{}.{}.tmp", std::process::id(), count);
+        fs::write(dir.join(name), "not ours").unwrap();
+    }
+
+    let result = save_dogs(path.to_str().unwrap(), &dogs());
+    assert!(matches!(result, Err(GetDogsError::CannotWrite(_))));
+    assert!(!path.exists());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 64);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn directory_target_is_cannot_rename() {
+    let dir = temp_dir("dir-target");
+    let target = dir.join("dogs.json");
+    fs::create_dir(&target).unwrap();
+    fs::write(target.join("keep"), "").unwrap();
+
+    let result = save_dogs(target.to_str().unwrap(), &dogs());
+    assert!(matches!(result, Err(GetDogsError::CannotRename(_))));
+    // The temporary file is cleaned up after the failure.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    fs::remove_dir_all(dir).unwrap();
+}
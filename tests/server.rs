@@ -0,0 +1,183 @@
+use rust_error_handling::{get_dogs3, Context, GetDogsError, ProblemDetails};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+mod common;
+use common::temp_dir;
+
+// The server is killed when the test finishes, even if it fails.
+struct Server {
+    child: Child,
+    addr: String,
+}
+
+impl Server {
+    // Port 0 lets the tests run in parallel without clashing.
+    fn start(file: &str) -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_dog-server"))
+            .args(["--file", file, "--addr", "127.0.0.1:0"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line.trim().trim_start_matches("listening on http://");
+        Server {
+            addr: addr.to_string(),
+            child,
+        }
+    }
+
+    // Returns the status, the content type and the body.
+    fn request(&self, method: &str, path: &str, body: Option<Value>) -> (u16, String, String) {
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let mut stream = TcpStream::connect(&self.addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head[9..12].parse().unwrap();
+        let content_type = head
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Type: "))
+            .unwrap_or_default();
+        (status, content_type.to_string(), body.to_string())
+    }
+
+    // Requests that fail must answer with problem details.
+    fn problem(&self, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+        let (status, content_type, body) = self.request(method, path, body);
+        assert_eq!(content_type, "application/problem+json");
+        let problem: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(problem["status"], status);
+        (status, problem)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn temp_copy(name: &str, fixture: &str) -> PathBuf {
+    let path = temp_dir(name).join("dogs.json");
+    fs::copy(fixture, &path).unwrap();
+    path
+}
+
+#[test]
+fn dogs_can_be_listed_added_changed_and_removed() {
+    let path = temp_copy("crud", "tests/fixtures/dogs.json");
+    let server = Server::start(path.to_str().unwrap());
+
+    let (status, content_type, body) = server.request("GET", "/dogs", None);
+    assert_eq!((status, content_type.as_str()), (200, "application/json"));
+    let dogs: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(dogs[0]["name"], "Comet");
+
+    let rex = json!({ "name": "Sir Rex", "breed": "Boxer" });
+    assert_eq!(server.request("POST", "/dogs", Some(rex.clone())).0, 201);
+    let (status, problem) = server.problem("POST", "/dogs", Some(rex));
+    assert_eq!((status, &problem["code"]), (409, &json!("E015")));
+
+    let rex = json!({ "name": "Sir Rex", "breed": "Boxer", "sex": "male" });
+    assert_eq!(server.request("PUT", "/dogs/Sir%20Rex", Some(rex)).0, 200);
+    let (status, _, body) = server.request("GET", "/dogs/Sir%20Rex", None);
+    assert_eq!(status, 200);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["sex"], "male");
+    assert_eq!(get_dogs3(path.to_str().unwrap()).unwrap().len(), 3);
+
+    assert_eq!(server.request("DELETE", "/dogs/Sir%20Rex", None).0, 204);
+    let (status, problem) = server.problem("DELETE", "/dogs/Sir%20Rex", None);
+    assert_eq!((status, &problem["title"]), (404, &json!("Dog not found")));
+    assert_eq!(server.problem("GET", "/dogs/Sir%20Rex", None).0, 404);
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn bad_requests_are_rejected() {
+    let path = temp_copy("bad-requests", "tests/fixtures/dogs.json");
+    let server = Server::start(path.to_str().unwrap());
+
+    let (status, problem) = server.problem("POST", "/dogs", Some(json!({ "name": "Rex" })));
+    assert_eq!(status, 400);
+    assert!(problem["detail"].as_str().unwrap().contains("breed"));
+
+    let (status, problem) = server.problem(
+        "POST",
+        "/dogs",
+        Some(json!({ "name": "", "breed": "Boxer" })),
+    );
+    assert_eq!(status, 422);
+    assert_eq!(problem["violations"][0]["field"], "name");
+
+    let comet = json!({ "name": "Comet", "breed": "Whippet" });
+    assert_eq!(server.problem("PUT", "/dogs/Oscar", Some(comet)).0, 400);
+    assert_eq!(server.problem("PATCH", "/dogs", None).0, 405);
+    assert_eq!(server.problem("GET", "/cats", None).0, 404);
+
+    // Nothing was changed by the bad requests.
+    assert_eq!(get_dogs3(path.to_str().unwrap()).unwrap().len(), 2);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn file_errors_map_to_statuses() {
+    let server = Server::start("tests/fixtures/missing.json");
+    let (status, problem) = server.problem("GET", "/dogs", None);
+    assert_eq!(status, 503);
+    assert_eq!(problem["code"], "E016");
+    assert_eq!(problem["title"], "Dogs file is missing");
+    assert_eq!(problem["type"], "urn:rust-error-handling:error:E016");
+
+    let server = Server::start("tests/fixtures/malformed.json");
+    let (status, problem) = server.problem("GET", "/dogs", None);
+    assert_eq!((status, &problem["code"]), (500, &json!("E004")));
+
+    let server = Server::start("tests/fixtures/invalid.json");
+    let (status, problem) = server.problem("GET", "/dogs", None);
+    assert_eq!(status, 422);
+    assert!(!problem["violations"].as_array().unwrap().is_empty());
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_count_failures_by_variant() {
+    let server = Server::start("tests/fixtures/malformed.json");
+    server.problem("GET", "/dogs", None);
+    server.problem("GET", "/dogs/Comet", None);
+    let (status, content_type, body) = server.request("GET", "/metrics", None);
+    assert_eq!(status, 200);
+    assert!(content_type.starts_with("text/plain"));
+    assert!(body.contains("dogs_operation_failures_total{operation=\"load\",kind=\"BadJson\"} 2\n"));
+}
+
+#[test]
+fn context_keeps_the_status_of_the_root() {
+    let err = get_dogs3("tests/fixtures/missing.json")
+        .context("loading dogs")
+        .unwrap_err();
+    assert!(matches!(err, GetDogsError::WithContext { .. }));
+    let problem = ProblemDetails::new(&err);
+    assert_eq!(problem.status, 503);
+    assert!(!problem.detail.contains("loading dogs"));
+}
@@ -0,0 +1,138 @@
+use rust_error_handling::{
+    Dog, DogStore, GetDogsError, JsonFileStore, MemoryStore, Sex, StoreProblem, CURRENT_VERSION,
+};
+use std::fs;
+
+mod common;
+use common::temp_dir;
+
+// Every backend must behave the same way, so they all run this.
+fn exercise(store: &mut dyn DogStore, backend: &str) {
+    assert!(store.list().unwrap().is_empty());
+
+    store.insert(Dog::new("Comet", "Whippet")).unwrap();
+    store
+        .insert(Dog::new("Oscar", "German Shorthaired Pointer"))
+        .unwrap();
+    assert_eq!(
+        store.get("Comet").unwrap(),
+        Some(Dog::new("Comet", "Whippet"))
+    );
+    assert_eq!(store.get("Rex").unwrap(), None);
+
+    match store.insert(Dog::new("Comet", "Greyhound")) {
+        Err(GetDogsError::Storage {
+            backend: b,
+            problem: StoreProblem::Duplicate(name),
+        }) => assert_eq!((b, name.as_str()), (backend, "Comet")),
+        other => panic!("expected a duplicate, got {:?}", other),
+    }
+    // A taken name is reported before the dog is validated.
+    assert!(matches!(
+        store.insert(Dog::new("Comet", "")),
+        Err(GetDogsError::Storage {
+            problem: StoreProblem::Duplicate(_),
+            ..
+        })
+    ));
+
+    // Updating keeps the dog in the same position.
+    let comet = Dog::new("Comet", "Whippet").with_sex(Sex::Female);
+    store.update(comet.clone()).unwrap();
+    let dogs = store.list().unwrap();
+    assert_eq!(dogs[0], comet);
+    assert_eq!(dogs.len(), 2);
+
+    match store.update(Dog::new("Rex", "Boxer")) {
+        Err(e @ GetDogsError::Storage { .. }) => {
+            assert_eq!(e.exit_code(), 15);
+            assert_eq!(
+                e.to_string(),
+                format!("{} storage: no dog is named \"Rex\"", backend)
+            );
+        }
+        other => panic!("expected a missing dog, got {:?}", other),
+    }
+    assert!(matches!(
+        store.insert(Dog::new("", "Boxer")),
+        Err(GetDogsError::Invalid(_))
+    ));
+
+    store.delete("Comet").unwrap();
+    assert!(matches!(
+        store.delete("Comet"),
+        Err(GetDogsError::Storage {
+            problem: StoreProblem::Missing(_),
+            ..
+        })
+    ));
+    assert_eq!(
+        store.list().unwrap(),
+        vec![Dog::new("Oscar", "German Shorthaired Pointer")]
+    );
+}
+
+#[test]
+fn memory_store() {
+    exercise(&mut MemoryStore::new(), "memory");
+}
+
+#[test]
+fn json_file_store() {
+    let dir = temp_dir("json");
+    let path = dir.join("dogs.json");
+    let mut store = JsonFileStore::new(path.to_str().unwrap());
+    exercise(&mut store, "json");
+
+    // The file is written in the current format.
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(saved["version"], CURRENT_VERSION);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn json_file_store_reports_bad_files() {
+    let store = JsonFileStore::new("tests/fixtures/malformed.json");
+    assert!(matches!(store.list(), Err(GetDogsError::BadJson(_))));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn sqlite_store() {
+    use rust_error_handling::SqliteStore;
+
+    exercise(&mut SqliteStore::open_in_memory().unwrap(), "sqlite");
+
+    // Dogs are still there when the database is opened again.
+    let dir = temp_dir("sqlite");
+    let path = dir.join("dogs.db");
+    let path = path.to_str().unwrap();
+    SqliteStore::open(path)
+        .unwrap()
+        .insert(Dog::new("Comet", "Whippet"))
+        .unwrap();
+    assert_eq!(
+        SqliteStore::open(path).unwrap().list().unwrap(),
+        vec![Dog::new("Comet", "Whippet")]
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn sqlite_failures_are_storage_errors() {
+    use rust_error_handling::SqliteStore;
+
+    let dir = temp_dir("sqlite-bad");
+    match SqliteStore::open(dir.join("missing").join("dogs.db").to_str().unwrap()) {
+        Err(
+            e @ GetDogsError::Storage {
+                backend: "sqlite",
+                problem: StoreProblem::Backend(_),
+            },
+        ) => assert!(std::error::Error::source(&e).is_some()),
+        other => panic!("expected a backend error, got {:?}", other.err()),
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
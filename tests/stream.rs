@@ -0,0 +1,235 @@
+use rust_error_handling::{stream_dogs, Dog, GetDogsError, MyResult, SyntaxProblem};
+use std::fs::File;
+use std::io::{self, Read};
+
+mod common;
+use common::dogs;
+
+fn collect(text: &str) -> Vec<MyResult<Dog>> {
+    stream_dogs(text.as_bytes()).collect()
+}
+
+// This returns the index and position of a parse error, and the error they wrap.
+fn in_stream(result: &MyResult<Dog>) -> (Option<usize>, usize, usize, &GetDogsError) {
+    match result {
+        Err(GetDogsError::InStream {
+            index,
+            line,
+            column,
+            source,
+        }) => (*index, *line, *column, source),
+        other => panic!("expected InStream, got {:?}", other),
+    }
+}
+
+#[test]
+fn streams_json_array_and_ndjson_files() {
+    for path in &["tests/fixtures/dogs.json", "tests/fixtures/dogs.ndjson"] {
+        let streamed: MyResult<Vec<Dog>> = stream_dogs(File::open(path).unwrap()).collect();
+        assert_eq!(streamed.unwrap(), dogs(), "{}", path);
+    }
+}
+
+#[test]
+fn empty_inputs_have_no_dogs() {
+    assert!(collect("").is_empty());
+    assert!(collect(" [ ] ").is_empty());
+}
+
+#[test]
+fn brackets_and_commas_in_strings_are_not_structure() {
+    let dogs = collect(r#"[{"name": "Sir, \"Rex]\"", "breed": "Boxer", "extra": [1, {"a": 2}]}]"#);
+    assert_eq!(dogs.len(), 1);
+    assert!(matches!(dogs[0], Err(GetDogsError::Invalid(_))));
+}
+
+#[test]
+fn bad_dogs_do_not_stop_the_stream() {
+    let dogs = collect(
+        r#"[{"name": "Rex"}, {"name": "", "breed": "Boxer"}, {"name": "Comet", "breed": "Whippet"}]"#,
+    );
+    assert_eq!(dogs.len(), 3);
+    let (index, _, _, source) = in_stream(&dogs[0]);
+    assert_eq!(index, Some(0));
+    assert!(matches!(source, GetDogsError::BadJson(_)));
+    match dogs[1] {
+        Err(GetDogsError::Invalid(ref violations)) => assert_eq!(violations[0].index, 1),
+        ref other => panic!("expected Invalid, got {:?}", other),
+    }
+    assert_eq!(*dogs[2].as_ref().unwrap(), Dog::new("Comet", "Whippet"));
+}
+
+#[test]
+fn truncated_array_ends_with_bad_syntax() {
+    let dogs = collect(r#"[{"name": "Comet", "breed": "Whippet"}, {"name": "#);
+    assert_eq!(dogs.len(), 2);
+    assert!(dogs[0].is_ok());
+    let (index, line, column, source) = in_stream(&dogs[1]);
+    assert_eq!((index, line, column), (Some(1), 1, 50));
+    assert!(matches!(
+        source,
+        GetDogsError::BadSyntax(SyntaxProblem::Truncated)
+    ));
+    let err = dogs[1].as_ref().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "dog 1 at line 1 column 50: bad JSON: the input ends before the dogs do"
+    );
+    assert_eq!(err.exit_code(), 29);
+    assert_eq!(err.kind(), "BadSyntax");
+}
+
+#[test]
+fn syntax_errors_have_the_index_and_position_in_the_file() {
+    let dogs = collect(
+        "[\n  {\"name\": \"Comet\", \"breed\": \"Whippet\"},\n  {\"name\": \"Rex\" \"breed\": \"Boxer\"}\n]",
+    );
+    assert_eq!(dogs.len(), 2);
+    assert!(dogs[0].is_ok());
+    let (index, line, column, source) = in_stream(&dogs[1]);
+    assert_eq!((index, line, column), (Some(1), 3, 18));
+    // The wrapped error counts from the start of the dog.
+    match source {
+        GetDogsError::BadJson(e) => assert_eq!((e.line(), e.column()), (1, 16)),
+        other => panic!("expected BadJson, got {:?}", other),
+    }
+    let err = dogs[1].as_ref().unwrap_err();
+    assert_eq!(err.exit_code(), 4);
+    assert_eq!(err.kind(), "BadJson");
+    assert!(err
+        .to_string()
+        .starts_with("dog 1 at line 3 column 18: bad JSON: "));
+
+    // Each line of NDJSON is positioned in the whole file.
+    let dogs = collect("{\"name\": \"Comet\", \"breed\": \"Whippet\"}\n\n{\"name\": 7}\n");
+    let (index, line, column, source) = in_stream(&dogs[1]);
+    assert_eq!((index, line), (Some(1), 3));
+    assert!(column > 1);
+    assert!(matches!(source, GetDogsError::BadField { .. }));
+}
+
+#[test]
+fn trailing_commas_are_bad_syntax() {
+    let dogs = collect(r#"[{"name": "Comet", "breed": "Whippet"}, ]"#);
+    assert_eq!(dogs.len(), 2);
+    assert!(dogs[0].is_ok());
+    let (index, line, column, source) = in_stream(&dogs[1]);
+    assert_eq!((index, line, column), (None, 1, 41));
+    assert!(matches!(
+        source,
+        GetDogsError::BadSyntax(SyntaxProblem::TrailingComma)
+    ));
+    assert_eq!(
+        dogs[1].as_ref().unwrap_err().to_string(),
+        "line 1 column 41: bad JSON: trailing comma after the last dog"
+    );
+}
+
+#[test]
+fn text_after_the_array_is_bad_syntax() {
+    let dogs = collect("[{\"name\": \"Comet\", \"breed\": \"Whippet\"}]\n  x");
+    assert_eq!(dogs.len(), 2);
+    assert!(dogs[0].is_ok());
+    let (index, line, column, source) = in_stream(&dogs[1]);
+    assert_eq!((index, line, column), (None, 2, 3));
+    assert!(matches!(
+        source,
+        GetDogsError::BadSyntax(SyntaxProblem::TrailingText)
+    ));
+    assert_eq!(
+        dogs[1].as_ref().unwrap_err().to_string(),
+        "line 2 column 3: bad JSON: text after the end of the dogs"
+    );
+
+    let dogs = collect("[] []");
+    assert_eq!(dogs.len(), 1);
+    assert_eq!(in_stream(&dogs[0]).2, 4);
+    assert!(collect("[]\n").is_empty());
+}
+
+#[test]
+fn other_top_level_values_are_bad_syntax() {
+    let dogs = collect("42");
+    assert_eq!(dogs.len(), 1);
+    let (index, _, _, source) = in_stream(&dogs[0]);
+    assert_eq!(index, None);
+    assert!(matches!(source, GetDogsError::BadSyntax(_)));
+    assert_eq!(
+        dogs[0].as_ref().unwrap_err().to_string(),
+        "line 1 column 1: bad JSON: expected `[` or `{`"
+    );
+}
+
+#[test]
+fn versioned_documents_stream_their_dogs() {
+    let file = File::open("tests/fixtures/envelope.json").unwrap();
+    let streamed: MyResult<Vec<Dog>> = stream_dogs(file).collect();
+    assert_eq!(streamed.unwrap(), dogs());
+
+    let compact = r#"{"version":2,"dogs":[{"name":"Comet","breed":"Whippet"}]} "#;
+    let dogs = collect(compact);
+    assert_eq!(dogs.len(), 1);
+    assert_eq!(*dogs[0].as_ref().unwrap(), Dog::new("Comet", "Whippet"));
+    assert!(collect(r#"{ "version": 2, "dogs": [] }"#).is_empty());
+}
+
+#[test]
+fn versioned_documents_fail_once() {
+    let message = |text: &str| {
+        let dogs = collect(text);
+        assert_eq!(dogs.len(), 1, "{}", text);
+        dogs[0].as_ref().unwrap_err().to_string()
+    };
+    assert_eq!(
+        message(r#"{"version": 2, "dogs": []} x"#),
+        "line 1 column 28: bad JSON: text after the end of the dogs"
+    );
+    assert_eq!(
+        message(r#"{"version": 2, "dogs": [], "extra": 1}"#),
+        "line 1 column 26: bad JSON: expected `}`"
+    );
+    assert_eq!(
+        message(r#"{"version": 2, "cats": []}"#),
+        "line 1 column 17: bad JSON: expected `\"dogs\"`"
+    );
+    assert_eq!(
+        message(r#"{"dogs": [], "version": 2}"#),
+        "line 1 column 8: bad JSON: the version must come before the dogs to stream them"
+    );
+
+    let error = |path: &str| {
+        let dogs: Vec<_> = stream_dogs(File::open(path).unwrap()).collect();
+        assert_eq!(dogs.len(), 1, "{}", path);
+        dogs.into_iter().next().unwrap().unwrap_err()
+    };
+    assert!(matches!(
+        error("tests/fixtures/future_version.json"),
+        GetDogsError::UnsupportedVersion(_)
+    ));
+    assert!(matches!(
+        error("tests/fixtures/v1_object.json"),
+        GetDogsError::MigrationFailed { from: 1, to: 2, .. }
+    ));
+}
+
+// This reader fails after returning some of its input.
+struct Failing<'a>(&'a [u8]);
+
+impl Read for Failing<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() {
+            return Err(io::Error::other("disk on fire"));
+        }
+        let n = self.0.read(buf)?;
+        Ok(n)
+    }
+}
+
+#[test]
+fn read_errors_end_the_stream() {
+    let input = br#"[{"name": "Comet", "breed": "Whippet"}, "#;
+    let dogs: Vec<_> = stream_dogs(Failing(input)).collect();
+    assert_eq!(dogs.len(), 2);
+    assert!(dogs[0].is_ok());
+    assert!(matches!(dogs[1], Err(GetDogsError::BadFile(_))));
+}
@@ -0,0 +1,152 @@
+use rust_error_handling::{get_dogs1, get_dogs3, load_dogs, save_dogs, stream_dogs, Mode};
+use serde_json::Value;
+use std::io::Write;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::fmt::format::FmtSpan;
+
+mod common;
+use common::temp_dir;
+
+// This collects the JSON logs written while running f, one value per line.
+fn capture(f: impl FnOnce()) -> Vec<Value> {
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(move || writer.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, f);
+
+    let logs = buffer.0.lock().unwrap().clone();
+    String::from_utf8(logs)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn closed<'a>(logs: &'a [Value], name: &str) -> &'a Value {
+    logs.iter()
+        .find(|log| log["fields"]["message"] == "close" && log["span"]["name"] == name)
+        .unwrap()
+}
+
+#[test]
+fn loads_record_their_size() {
+    let logs = capture(|| {
+        get_dogs3("tests/fixtures/dogs.json").unwrap();
+    });
+    let span = &closed(&logs, "load")["span"];
+    assert_eq!(span["path"], "tests/fixtures/dogs.json");
+    assert_eq!(span["format"], "json");
+    assert_eq!(span["dogs"], 2);
+    assert!(span["bytes"].as_u64().unwrap() > 0);
+    assert!(span["duration_ms"].as_f64().is_some());
+}
+
+#[test]
+fn errors_are_events_with_their_kind() {
+    let logs = capture(|| {
+        get_dogs3("tests/fixtures/malformed.json").unwrap_err();
+    });
+    let error = logs.iter().find(|log| log["level"] == "ERROR").unwrap();
+    assert_eq!(error["fields"]["kind"], "BadJson");
+    assert_eq!(error["fields"]["code"], "E004");
+    assert_eq!(error["span"]["name"], "load");
+}
+
+#[test]
+fn lenient_loads_report_each_bad_dog() {
+    let logs = capture(|| {
+        let loaded = load_dogs("tests/fixtures/mixed.json", Mode::Lenient).unwrap();
+        assert!(!loaded.errors.is_empty());
+    });
+    let warnings: Vec<&Value> = logs.iter().filter(|log| log["level"] == "WARN").collect();
+    assert!(!warnings.is_empty());
+    assert!(warnings.iter().all(|w| w["fields"]["index"].is_u64()));
+    assert!(logs.iter().all(|log| log["level"] != "ERROR"));
+}
+
+#[test]
+fn get_dogs1_runs_in_a_load_span() {
+    let logs = capture(|| {
+        get_dogs1("tests/fixtures/dogs.json").unwrap();
+    });
+    let span = &closed(&logs, "load")["span"];
+    assert_eq!(span["path"], "tests/fixtures/dogs.json");
+    assert_eq!(span["dogs"], 2);
+    assert!(span["bytes"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn streams_are_loads_that_warn_about_bad_dogs() {
+    let ndjson = std::fs::read("tests/fixtures/malformed.ndjson").unwrap();
+    let logs = capture(|| {
+        let streamed: Vec<_> = stream_dogs(&ndjson[..]).collect();
+        assert_eq!(streamed.len(), 2);
+    });
+    let span = &closed(&logs, "load")["span"];
+    assert_eq!(span["format"], "ndjson");
+    assert_eq!(span["dogs"], 1);
+    assert_eq!(span["bytes"].as_u64().unwrap(), ndjson.len() as u64);
+    let warning = logs.iter().find(|log| log["level"] == "WARN").unwrap();
+    assert_eq!(warning["fields"]["kind"], "BadJson");
+    assert_eq!(warning["fields"]["index"], 1);
+    assert!(logs.iter().all(|log| log["level"] != "ERROR"));
+}
+
+#[test]
+fn saves_record_the_bytes_written() {
+    let dir = temp_dir("save");
+    let path = dir.join("dogs.json");
+    let path = path.to_str().unwrap();
+    let dogs = get_dogs3("tests/fixtures/dogs.json").unwrap();
+    let logs = capture(|| save_dogs(path, &dogs).unwrap());
+    let span = &closed(&logs, "save")["span"];
+    assert_eq!(span["dogs"], 2);
+    assert_eq!(
+        span["bytes"].as_u64().unwrap(),
+        std::fs::metadata(path).unwrap().len()
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_tool_writes_json_logs_to_stderr() {
+    let result = Command::new(env!("CARGO_BIN_EXE_rust-error-handling"))
+        .args(["-f", "tests/fixtures/missing.json", "list"])
+        .args(["--log-level", "info", "--log-format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(result.status.code(), Some(16));
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    let logs: Vec<Value> = stderr
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    assert!(logs.iter().any(|log| log["fields"]["kind"] == "NotFound"));
+
+    // Nothing is logged by default.
+    let result = Command::new(env!("CARGO_BIN_EXE_rust-error-handling"))
+        .args(["-f", "tests/fixtures/missing.json", "list", "--json-errors"])
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1);
+}
@@ -0,0 +1,92 @@
+use rust_error_handling::{
+    check_unique, dedupe, find_duplicates, get_dogs3, DedupeStrategy, Dog, DogStore, Duplicate,
+    ErrorReport, GetDogsError, MemoryStore, UniqueKey, UniqueStore,
+};
+
+fn dogs() -> Vec<Dog> {
+    get_dogs3("tests/fixtures/duplicates.json").unwrap()
+}
+
+fn pairs(key: UniqueKey) -> Vec<(usize, usize)> {
+    find_duplicates(&dogs(), key)
+        .iter()
+        .map(|d| (d.first, d.second))
+        .collect()
+}
+
+#[test]
+fn duplicates_are_found_by_each_key() {
+    assert_eq!(pairs(UniqueKey::Name), vec![(0, 2), (0, 3)]);
+    assert_eq!(pairs(UniqueKey::NameAndBreed), vec![(0, 2)]);
+    assert_eq!(pairs(UniqueKey::Microchip), vec![(1, 4)]);
+    assert!(find_duplicates(&dogs()[..2], UniqueKey::Name).is_empty());
+}
+
+#[test]
+fn every_duplicate_is_reported() {
+    let err = check_unique(&dogs(), UniqueKey::Name).unwrap_err();
+    match err {
+        GetDogsError::Duplicates {
+            key,
+            ref duplicates,
+        } => {
+            assert_eq!(key, UniqueKey::Name);
+            assert_eq!(
+                duplicates[1],
+                Duplicate {
+                    first: 0,
+                    second: 3,
+                    value: "Comet".to_string()
+                }
+            );
+        }
+        ref other => panic!("expected Duplicates, got {:?}", other),
+    }
+    assert_eq!(err.exit_code(), 22);
+    assert_eq!(
+        err.to_string(),
+        "duplicate dogs by name:\n  dogs 0 and 2 are both \"Comet\"\n  dogs 0 and 3 are both \"Comet\""
+    );
+    let report = ErrorReport::new(&err, None);
+    assert_eq!(report.violations.len(), 2);
+    assert_eq!(report.violations[0].index, 2);
+}
+
+#[test]
+fn dedupe_strategies() {
+    let first = dedupe(dogs(), UniqueKey::NameAndBreed, DedupeStrategy::KeepFirst);
+    assert_eq!(first.len(), 4);
+    assert_eq!(first[0], dogs()[0]);
+
+    let last = dedupe(dogs(), UniqueKey::NameAndBreed, DedupeStrategy::KeepLast);
+    assert_eq!(last[0], dogs()[2]);
+    assert_eq!(last[1].name(), "Oscar");
+
+    // Merging keeps the first dog's sex but takes the weight it lacked.
+    let merged = dedupe(dogs(), UniqueKey::NameAndBreed, DedupeStrategy::Merge);
+    assert_eq!(merged[0], dogs()[0].clone().with_weight(12.5));
+
+    // Dogs without microchips are all kept.
+    let merged = dedupe(dogs(), UniqueKey::Microchip, DedupeStrategy::Merge);
+    assert_eq!(merged.len(), 4);
+    assert!(find_duplicates(&merged, UniqueKey::Microchip).is_empty());
+}
+
+#[test]
+fn unique_stores_reject_duplicates_on_insert_and_load() {
+    let mut store = UniqueStore::new(MemoryStore::new(), UniqueKey::Microchip);
+    let chipped = |name: &str| Dog::new(name, "Boxer").with_microchip("985112345678901");
+    store.insert(chipped("Rex")).unwrap();
+    match store.insert(chipped("Max")) {
+        Err(GetDogsError::Duplicates { duplicates, .. }) => {
+            assert_eq!((duplicates[0].first, duplicates[0].second), (0, 1));
+        }
+        other => panic!("expected Duplicates, got {:?}", other),
+    }
+    // Updating a dog doesn't make it a duplicate of itself.
+    store.update(chipped("Rex").with_owner("Mark")).unwrap();
+    assert_eq!(store.list().unwrap().len(), 1);
+
+    let store = UniqueStore::new(MemoryStore::with_dogs(dogs()), UniqueKey::Name);
+    assert!(matches!(store.list(), Err(GetDogsError::Duplicates { .. })));
+}
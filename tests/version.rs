@@ -0,0 +1,56 @@
+use rust_error_handling::{
+    get_dogs, get_dogs3, load_dogs, migrations, GetDogsError, Mode, CURRENT_VERSION,
+};
+
+mod common;
+use common::dogs;
+
+#[test]
+fn bare_arrays_and_envelopes_load_the_same() {
+    assert_eq!(get_dogs3("tests/fixtures/dogs.json").unwrap(), dogs());
+    assert_eq!(get_dogs3("tests/fixtures/envelope.json").unwrap(), dogs());
+    assert_eq!(
+        get_dogs("tests/fixtures/envelope.json", None).unwrap(),
+        dogs()
+    );
+    let loaded = load_dogs("tests/fixtures/envelope.json", Mode::Lenient).unwrap();
+    assert_eq!(loaded.dogs, dogs());
+}
+
+#[test]
+fn every_older_version_has_a_migration() {
+    let from: Vec<u32> = migrations().iter().map(|m| m.from).collect();
+    let expected: Vec<u32> = (1..CURRENT_VERSION).collect();
+    assert_eq!(from, expected);
+}
+
+#[test]
+fn future_versions_are_unsupported() {
+    match get_dogs3("tests/fixtures/future_version.json") {
+        Err(GetDogsError::UnsupportedVersion(version)) => assert_eq!(version, "3"),
+        other => panic!("expected UnsupportedVersion, got {:?}", other),
+    }
+}
+
+#[test]
+fn failed_migrations_name_the_versions() {
+    match get_dogs3("tests/fixtures/v1_object.json") {
+        Err(GetDogsError::MigrationFailed { from, to, .. }) => assert_eq!((from, to), (1, 2)),
+        other => panic!("expected MigrationFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn bad_fields_in_envelopes_keep_their_position() {
+    match get_dogs3("tests/fixtures/envelope_bad_date.json") {
+        Err(GetDogsError::BadField {
+            index,
+            field,
+            source,
+        }) => {
+            assert_eq!((index, field.as_str()), (0, "birth_date"));
+            assert_eq!(source.line(), 7);
+        }
+        other => panic!("expected BadField, got {:?}", other),
+    }
+}